@@ -0,0 +1,73 @@
+//! Fails the build if a `t!` key used in `src/` is missing from a locale file.
+//!
+//! rust-i18n shows the bare key for a missing translation, which is easy to miss in
+//! a language nobody on the team reads.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+const MACRO: &str = "t!(\"";
+
+fn rust_files(dir: &Path, files: &mut Vec<String>) {
+    for entry in fs::read_dir(dir).expect("read src").flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            rust_files(&path, files);
+        } else if path.extension().is_some_and(|extension| extension == "rs") {
+            files.push(fs::read_to_string(&path).expect("read source file"));
+        }
+    }
+}
+
+/// Literal keys of all `t!` calls, `format!` and other macros ending in `t` are skipped.
+fn used_keys(source: &str) -> BTreeSet<String> {
+    let mut keys = BTreeSet::new();
+    for (start, _) in source.match_indices(MACRO) {
+        let before = source[..start].chars().next_back();
+        if before.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            continue;
+        }
+        let rest = &source[start + MACRO.len()..];
+        if let Some(end) = rest.find('"') {
+            keys.insert(rest[..end].to_string());
+        }
+    }
+    keys
+}
+
+/// Top level keys of a flat locale file.
+fn locale_keys(yaml: &str) -> BTreeSet<String> {
+    yaml.lines()
+        .filter(|line| !line.starts_with([' ', '\t', '#']))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, _)| key.trim().to_string())
+        .collect()
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=locales");
+
+    let mut sources = Vec::new();
+    rust_files(Path::new("src"), &mut sources);
+    let used: BTreeSet<String> = sources.iter().flat_map(|source| used_keys(source)).collect();
+
+    let mut missing = Vec::new();
+    let mut locales: Vec<_> = fs::read_dir("locales")
+        .expect("read locales")
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "yaml"))
+        .collect();
+    locales.sort();
+    for path in locales {
+        let keys = locale_keys(&fs::read_to_string(&path).expect("read locale file"));
+        for key in used.difference(&keys) {
+            missing.push(format!("{}: {}", path.display(), key));
+        }
+    }
+    if !missing.is_empty() {
+        panic!("translations missing:\n{}", missing.join("\n"));
+    }
+}
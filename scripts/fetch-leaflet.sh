@@ -0,0 +1,33 @@
+#!/bin/sh
+# Downloads the Leaflet release the `leaflet` crate binds to into assets/leaflet,
+# checking the files against the same hashes index.html pins them to.
+set -eu
+
+VERSION=1.9.3
+BASE="https://unpkg.com/leaflet@${VERSION}/dist"
+TARGET="$(dirname "$0")/../assets/leaflet"
+
+mkdir -p "$TARGET/images"
+
+fetch() {
+    curl -fsSL "$BASE/$1" -o "$TARGET/$1"
+}
+
+verify() {
+    actual="sha512-$(openssl dgst -sha512 -binary "$TARGET/$1" | openssl base64 -A)"
+    if [ "$actual" != "$2" ]; then
+        echo "Integrity check failed for $1: $actual" >&2
+        exit 1
+    fi
+}
+
+fetch leaflet.js
+verify leaflet.js "sha512-Dqm3h1Y4qiHUjbhxTuBGQsza0Tfppn53SHlu/uj1f+RT+xfShfe7r6czRf5r2NmllO2aKx+tYJgoxboOkn1Scg=="
+fetch leaflet.css
+verify leaflet.css "sha512-mD70nAW2ThLsWH0zif8JPbfraZ8hbCtjQ+5RU1m4+ztZq6/MymyZeB55pWsi4YAX+73yvcaJyk61mzfYMvtm9w=="
+
+for image in layers.png layers-2x.png marker-icon.png marker-icon-2x.png marker-shadow.png; do
+    fetch "images/$image"
+done
+
+echo "Leaflet $VERSION in $TARGET"
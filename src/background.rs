@@ -0,0 +1,80 @@
+use std::cell::RefCell;
+
+use chrono::Utc;
+use js_sys::{Function, Promise, Reflect};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{window, Event};
+
+use crate::utils::log_to_browser;
+
+/// Pages hidden for a shorter time in milliseconds did not miss enough to matter.
+const MIN_GAP: i64 = 3000;
+
+thread_local! {
+    /// The `WakeLockSentinel` while the screen is kept on.
+    static WAKE_LOCK: RefCell<Option<JsValue>> = const { RefCell::new(None) };
+}
+
+fn get(target: &JsValue, key: &str) -> Result<JsValue, JsValue> {
+    Reflect::get(target, &JsValue::from_str(key))
+}
+
+async fn request_wake_lock() -> Result<JsValue, JsValue> {
+    let navigator: JsValue = window().ok_or(JsValue::from_str("no window"))?.navigator().into();
+    let wake_lock = get(&navigator, "wakeLock")?;
+    if wake_lock.is_undefined() {
+        return Err(JsValue::from_str("Screen Wake Lock not supported"));
+    }
+    let request: Function = get(&wake_lock, "request")?.dyn_into()?;
+    let promise: Promise = request.call1(&wake_lock, &JsValue::from_str("screen"))?.dyn_into()?;
+    JsFuture::from(promise).await
+}
+
+/// Keeps the screen from turning off while `on`.
+///
+/// The browser drops the lock whenever the page is hidden, calling this again
+/// after the page came back requests a new one.
+pub fn keep_screen_on(on: bool) {
+    let held = WAKE_LOCK.with(|lock| {
+        lock.borrow()
+            .as_ref()
+            .is_some_and(|sentinel| get(sentinel, "released").ok().and_then(|r| r.as_bool()) == Some(false))
+    });
+    if on && !held {
+        spawn_local(async {
+            match request_wake_lock().await {
+                Ok(sentinel) => WAKE_LOCK.with(|lock| *lock.borrow_mut() = Some(sentinel)),
+                Err(err) => log_to_browser(format!("Wake lock failed: {:?}", err)),
+            }
+        });
+    } else if !on {
+        if let Some(sentinel) = WAKE_LOCK.with(|lock| lock.borrow_mut().take()) {
+            if let Ok(release) = get(&sentinel, "release").and_then(|r| r.dyn_into::<Function>()) {
+                let _ = release.call0(&sentinel);
+            }
+        }
+    }
+}
+
+/// Calls `on_resume` whenever the page becomes visible again.
+///
+/// The arguments are the unix times in milliseconds the page was hidden and shown,
+/// `None` for the first if it was hidden too briefly to have missed anything.
+pub fn watch_visibility(on_resume: impl Fn(Option<(i64, i64)>) + 'static) {
+    let Some(document) = window().and_then(|win| win.document()) else {
+        return;
+    };
+    let hidden_since = RefCell::new(None::<i64>);
+    let page = document.clone();
+    let cb: Closure<dyn Fn(Event)> = Closure::new(move |_: Event| {
+        let now = Utc::now().timestamp_millis();
+        if page.hidden() {
+            *hidden_since.borrow_mut() = Some(now);
+        } else if let Some(start) = hidden_since.borrow_mut().take() {
+            on_resume(Some((start, now)).filter(|(start, end)| end - start >= MIN_GAP));
+        }
+    });
+    let _ = document.add_event_listener_with_callback("visibilitychange", cb.as_ref().unchecked_ref());
+    cb.forget();
+}
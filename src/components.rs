@@ -1,28 +1,184 @@
 use sycamore::prelude::*;
 use rust_i18n::t;
-use web_sys::window;
+use wasm_bindgen::JsCast;
+use web_sys::{window, Event, HtmlSelectElement};
+
+use crate::units::{use_unit_system, Unit};
+pub mod deck;
+pub mod fields;
+pub mod guidance;
+pub mod hud;
+pub mod map;
+pub mod offline;
+pub mod permissions;
+pub mod profiles;
 pub mod raw_data;
+pub mod report;
+pub mod settings;
+
+/// Rounded to what the input shows, edits below this do not change the stored value.
+fn displayed(value: f64, unit: Unit) -> f64 {
+    (unit.convert(value) * 1000.0).round() / 1000.0
+}
+
+/// `step` converted to `unit` and rounded to one significant digit.
+fn displayed_step(step: f64, unit: Unit) -> f64 {
+    let step = unit.convert(step);
+    let magnitude = 10f64.powf(step.log10().floor());
+    (step / magnitude).round() * magnitude
+}
 
+fn range_text(min: f64, max: f64, unit: Unit) -> String {
+    if max.is_finite() {
+        format!("{} {} – {} {}", t!("input_range"), displayed(min, unit), displayed(max, unit), unit.symbol())
+    } else {
+        format!("{} ≥ {} {}", t!("input_range"), displayed(min, unit), unit.symbol())
+    }
+}
+
+/// Number input for a value stored in the base unit of `unit`.
+///
+/// Values outside `min`..`max` are clamped and not a number is rejected, both with a
+/// message below the input. The value is shown in the picked unit system, lengths and
+/// speeds can be entered in any unit of their kind.
 #[component(inline_props)]
-pub fn ValueInput<G: Html>(    
+pub fn ValueInput<G: Html>(
+    lable: String,
+    value: Signal<f64>,
+    #[prop(default)] unit: Unit,
+    #[prop(default = 0.0)] min: f64,
+    #[prop(default = f64::INFINITY)] max: f64,
+    #[prop(default = 0.1)] step: f64,
+) -> View<G> {
+    let system = use_unit_system();
+    let shown_unit = create_signal(unit.in_system(system.get_untracked()));
+    create_effect(move || shown_unit.set(unit.in_system(system.get())));
+    let shown = create_signal(displayed(value.get_untracked(), shown_unit.get_untracked()));
+    let error = create_signal(String::new());
+
+    // an edit is stored unless it only repeats the stored value
+    create_effect(move || {
+        let input = shown.get();
+        let unit = shown_unit.get_untracked();
+        if input.is_nan() {
+            error.set(t!("input_not_a_number"));
+            return;
+        }
+        let entered = unit.to_base(input);
+        let clamped = entered.clamp(min, max);
+        error.set(if clamped == entered { String::new() } else { range_text(min, max, unit) });
+        if displayed(clamped, unit) != displayed(value.get_untracked(), unit) {
+            value.set(clamped);
+        }
+    });
+
+    // other changes of the value and of the unit are shown
+    create_effect(move || {
+        let unit = shown_unit.get();
+        let current = value.get();
+        let input = shown.get_untracked();
+        if input.is_nan() || displayed(unit.to_base(input).clamp(min, max), unit) != displayed(current, unit) {
+            shown.set(displayed(current, unit));
+        }
+    });
+
+    // once the edit is done the input shows what was stored
+    let committed = move |_| shown.set(displayed(value.get_untracked(), shown_unit.get_untracked()));
+
+    let unit_picker = match unit.alternatives() {
+        [] => view! { div{(shown_unit.get().symbol())} },
+        alternatives => {
+            let options = View::new_fragment(
+                alternatives
+                    .iter()
+                    .enumerate()
+                    .map(|(index, option)| {
+                        let option = *option;
+                        view! {
+                            option(value=index.to_string(), selected=(shown_unit.get() == option)){(option.symbol())}
+                        }
+                    })
+                    .collect(),
+            );
+            let pick = move |event: Event| {
+                let picked = event
+                    .target()
+                    .and_then(|target| target.dyn_into::<HtmlSelectElement>().ok())
+                    .and_then(|select| select.value().parse::<usize>().ok())
+                    .and_then(|index| alternatives.get(index));
+                if let Some(picked) = picked {
+                    shown_unit.set(*picked);
+                }
+            };
+            view! { div{ select(on:change=pick){(options)} } }
+        }
+    };
+
+    view! {
+            span{(lable)}
+            input(bind:valueAsNumber=shown, on:change=committed, type="number",
+                min=(displayed(min, shown_unit.get()).to_string()),
+                max=(if max.is_finite() { displayed(max, shown_unit.get()).to_string() } else { String::new() }),
+                step=(displayed_step(step, shown_unit.get()).to_string()), size="8")
+            (unit_picker)
+            (if error.with(String::is_empty) {
+                view! {}
+            } else {
+                view! { span(class="input-error"){(error.get_clone())} }
+            })
+    }
+}
+
+#[component(inline_props)]
+pub fn CheckboxInput<G: Html>(lable: String, value: Signal<bool>) -> View<G> {
+    view! {
+            span{(lable)}
+            input(bind:checked=value, type="checkbox")
+            div{}
+    }
+}
+
+#[component(inline_props)]
+pub fn ValueOutput<G: Html>(
     children: Children<G>,
     lable: String,
-    value: Signal<f64>,) -> View<G> {
+    value: ReadSignal<f64>,
+    #[prop(default)] unit: Unit,
+) -> View<G> {
     let children = children.call();
+    let system = use_unit_system();
+    let unit = create_memo(move || unit.in_system(system.get()));
     view! {
             span{(lable)}
-            input(bind:valueAsNumber=value, type="number", min="0", step="0.1",maxlength="4",size="8")
-            div{(children)}
+            span{(((unit.get().convert(value.get())*100.0).round() / 100.0))}
+            div{(unit.get().symbol())(children)}
     }
 }
 
+/// Types that can be picked with a [`SelectInput`].
+pub trait SelectOption: Copy + PartialEq + 'static {
+    fn all() -> Vec<Self>;
+    fn lable(&self) -> String;
+}
+
 #[component(inline_props)]
-pub fn ValueOutput<G: Html>(children: Children<G>, lable: String, value: ReadSignal<f64>) -> View<G> {
-    let children = children.call();
+pub fn SelectInput<G: Html, T: SelectOption>(lable: String, value: Signal<T>) -> View<G> {
+    let buttons = View::new_fragment(
+        T::all()
+            .into_iter()
+            .map(|option| {
+                view! {
+                    button(class=(if value.get() == option { "selected" } else { "" }), on:click=move |_| value.set(option)){
+                        (option.lable())
+                    }
+                }
+            })
+            .collect(),
+    );
     view! {
             span{(lable)}
-            span{(((value.get()*100.0).round() / 100.0))}
-            div{(children)}
+            div(class="select-input"){(buttons)}
+            div{}
     }
 }
 
@@ -31,17 +187,17 @@ pub fn MenuButtons<G: Html>(raw_visable: Signal<bool>, menu_visable: Signal<bool
     view! {
         div(class="leaflet-bar leaflet-control map-button"){
             a (href="#", title=(if menu_visable.get(){
-                use rust_i18n::t;t!("close-menu")
+                t!("close_menu")
             }else{
-                t!("open-menu")
+                t!("open_menu")
             }), on:click=move |_| {
                 menu_visable.set(!menu_visable.get());
                 raw_visable.set(false);
             }){"⚙"}
             a (href="#", title=(if menu_visable.get(){
-                use rust_i18n::t;t!("close-raw")
+                t!("close_raw")
             }else{
-                t!("open-raw")
+                t!("open_raw")
             }), on:click=move |_| {
                 raw_visable.set(!raw_visable.get());
                 menu_visable.set(false);
@@ -0,0 +1,67 @@
+use chrono::Utc;
+use rust_i18n::t;
+use sycamore::prelude::*;
+
+use crate::{position::use_sensor_store, session::Session};
+
+/// Time in milliseconds the vibration has to stay above the threshold before the deck counts as engaged.
+const ENGAGE_TIME: i64 = 2000;
+/// Time in milliseconds the vibration has to stay below the release level before the deck counts as off.
+const RELEASE_TIME: i64 = 5000;
+/// Release level as a share of the threshold, so the state does not flicker around it.
+const RELEASE_FACTOR: f64 = 0.7;
+
+#[component(inline_props)]
+pub fn DeckButton<G: Html>(
+    engaged: Signal<bool>,
+    auto_detect: ReadSignal<bool>,
+    vibration_threshold: Signal<f64>,
+    session: ReadSignal<Session>,
+) -> View<G> {
+    let raw = use_sensor_store().raw;
+    // a manual toggle wins over the detection until the session ends or the setting changes,
+    // the stored setting itself stays as it is
+    let manual = create_signal(false);
+    let started = create_selector(move || session.with(|session| session.started));
+    create_effect(move || {
+        started.track();
+        auto_detect.track();
+        manual.set(false);
+    });
+    let detecting = create_selector(move || auto_detect.get() && !manual.get());
+    let level = create_selector(move || raw.with(|raw| raw.vibration as f64));
+    // start of the current run above the threshold or below the release level
+    let (mut above_since, mut below_since) = (None::<i64>, None::<i64>);
+    create_effect(move || {
+        let level = level.get();
+        if !detecting.get() {
+            (above_since, below_since) = (None, None);
+            return;
+        }
+        let now = Utc::now().timestamp_millis();
+        let threshold = vibration_threshold.get_untracked();
+        if level > threshold {
+            above_since.get_or_insert(now);
+            below_since = None;
+        } else if level < threshold * RELEASE_FACTOR {
+            below_since.get_or_insert(now);
+            above_since = None;
+        }
+        if above_since.is_some_and(|since| now - since >= ENGAGE_TIME) && !engaged.get_untracked() {
+            engaged.set(true);
+        }
+        if below_since.is_some_and(|since| now - since >= RELEASE_TIME) && engaged.get_untracked() {
+            engaged.set(false);
+        }
+    });
+
+    view! {
+        button(class=(if engaged.get() { "deck-button engaged" } else { "deck-button" }), on:click=move |_| {
+            manual.set(true);
+            engaged.set(!engaged.get());
+        }){
+            (if engaged.get() { t!("deck_engaged") } else { t!("deck_disengaged") })
+            (if detecting.get() { format!(" ({})", t!("auto")) } else { String::new() })
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use rust_i18n::t;
+use sycamore::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::{
+    database::{self, FIELDS, JOBS, SITES},
+    fields::{field_history, format_time, last_cut, Field, Job, Site},
+    geometry::Coverage,
+    projection::Projection,
+    report::{job_report_file_name, job_report_html},
+    session::Session,
+    units::use_unit_system,
+    utils::{download_file, log_to_browser},
+};
+
+#[component(inline_props)]
+pub fn FieldManager<G: Html>(
+    sites: Signal<Vec<Site>>,
+    fields: Signal<Vec<Field>>,
+    jobs: Signal<Vec<Job>>,
+    active_field: Signal<Option<i64>>,
+    session: Signal<Session>,
+    coverage: Signal<Option<Coverage>>,
+    projection: ReadSignal<Projection>,
+    gap_threshold: ReadSignal<f64>,
+    operator: ReadSignal<String>,
+) -> View<G> {
+    let system = use_unit_system();
+    let active_site = create_signal(None::<i64>);
+    let new_name = create_signal(String::new());
+
+    // follow the site of the field picked last time once everything is loaded
+    create_effect(move || {
+        if active_site.get_untracked().is_none() {
+            let site = active_field.with(|active| {
+                fields.with(|fields| {
+                    fields
+                        .iter()
+                        .find(|field| Some(field.id) == *active)
+                        .map(|field| field.site)
+                })
+            });
+            if site.is_some() {
+                active_site.set(site);
+            }
+        }
+    });
+
+    let site_buttons = move || {
+        View::new_fragment(sites.with(|sites| {
+            sites
+                .iter()
+                .map(|site| {
+                    let (id, name) = (site.id, site.name.clone());
+                    view! {
+                        button(class=(if active_site.get() == Some(id) { "selected" } else { "" }), on:click=move |_| active_site.set(Some(id))){
+                            (name.clone())
+                        }
+                    }
+                })
+                .collect()
+        }))
+    };
+
+    let field_rows = move || {
+        let site = active_site.get();
+        View::new_fragment(fields.with(|fields| {
+            fields
+                .iter()
+                .filter(|field| Some(field.site) == site)
+                .map(|field| {
+                    let (id, name) = (field.id, field.name.clone());
+                    let cut = jobs
+                        .with(|jobs| last_cut(jobs, id))
+                        .map_or_else(|| t!("never_cut"), format_time);
+                    let area = system.get().format_area(field.area(projection.get()));
+                    view! {
+                        button(class=(if active_field.get() == Some(id) { "selected" } else { "" }), on:click=move |_| active_field.set(Some(id))){
+                            (name.clone())
+                        }
+                        span{(cut)}
+                        span{(area)}
+                    }
+                })
+                .collect()
+        }))
+    };
+
+    let download_report = move |id: i64| {
+        let report = jobs.with(|jobs| {
+            let job = jobs.iter().find(|job| job.id == id)?;
+            fields.with(|fields| {
+                let field = fields.iter().find(|field| field.id == job.field);
+                let site = field.and_then(|field| sites.with(|sites| sites.iter().find(|site| site.id == field.site).cloned()));
+                let html = job_report_html(
+                    job,
+                    field,
+                    site.as_ref(),
+                    projection.get_untracked(),
+                    gap_threshold.get_untracked(),
+                    system.get_untracked(),
+                );
+                Some((job_report_file_name(job, field), html))
+            })
+        });
+        if let Some((file_name, html)) = report {
+            if let Err(err) = download_file(&file_name, "text/html", &html) {
+                log_to_browser(format!("Report download failed: {:?}", err));
+            }
+        }
+    };
+
+    let history = move || {
+        let Some(field) = active_field.get() else {
+            return View::empty();
+        };
+        View::new_fragment(jobs.with(|jobs| {
+            field_history(jobs, field)
+                .into_iter()
+                .map(|job| {
+                    let id = job.id;
+                    let entry = format!("{} – {}", format_time(job.finished()), system.get().format_area(job.area));
+                    view! {
+                        li{
+                            (entry)
+                            " "
+                            button(on:click=move |_| download_report(id)){(t!("job_report"))}
+                        }
+                    }
+                })
+                .collect()
+        }))
+    };
+
+    let add_site = move |_| {
+        let name = new_name.get_clone();
+        if !name.is_empty() {
+            let site = Site::new(name);
+            database::save(SITES, site.id, site.clone());
+            active_site.set(Some(site.id));
+            sites.update(|sites| sites.push(site));
+            new_name.set(String::new());
+        }
+    };
+
+    let add_field = move |_| {
+        let name = new_name.get_clone();
+        if let (false, Some(site)) = (name.is_empty(), active_site.get()) {
+            let field = Field::new(site, name);
+            database::save(FIELDS, field.id, field.clone());
+            active_field.set(Some(field.id));
+            fields.update(|fields| fields.push(field));
+            new_name.set(String::new());
+        }
+    };
+
+    let remove_field = move |_| {
+        if let Some(id) = active_field.get() {
+            // the history goes with the field, nothing else refers to its jobs
+            let job_ids: Vec<i64> = jobs.with(|jobs| {
+                jobs.iter().filter(|job| job.field == id).map(|job| job.id).collect()
+            });
+            spawn_local(async move {
+                for job in job_ids {
+                    if let Err(err) = database::delete(JOBS, job).await {
+                        log_to_browser(format!("Deleting job failed: {:?}", err));
+                    }
+                }
+                if let Err(err) = database::delete(FIELDS, id).await {
+                    log_to_browser(format!("Deleting field failed: {:?}", err));
+                }
+            });
+            jobs.update(|jobs| jobs.retain(|job| job.field != id));
+            fields.update(|fields| fields.retain(|field| field.id != id));
+            active_field.set(None);
+        }
+    };
+
+    let set_boundary = move |_| {
+        let Some(id) = active_field.get() else {
+            return;
+        };
+        coverage.with(|coverage| {
+            if let Some(coverage) = coverage {
+                fields.update(|fields| {
+                    if let Some(field) = fields.iter_mut().find(|field| field.id == id) {
+                        field.set_boundary_from(coverage);
+                        database::save(FIELDS, field.id, field.clone());
+                    }
+                });
+            }
+        });
+    };
+
+    let finish_job = move |_| {
+        let Some(field) = active_field.get() else {
+            return;
+        };
+        if session.with(|session| session.points.is_empty()) {
+            return;
+        }
+        let job = coverage.with(|coverage| {
+            Job::new(field, session.get_clone(), coverage.as_ref(), operator.get_clone())
+        });
+        database::save(JOBS, job.id, job.clone());
+        jobs.update(|jobs| jobs.push(job));
+        session.set(Session::new());
+        coverage.set(None);
+    };
+
+    view! {
+        div(class="field-manager"){
+            h3{(t!("sites"))}
+            div(class="select-input"){(site_buttons())}
+            h3{(t!("fields"))}
+            div(class="field-list"){(field_rows())}
+            div(class="select-input"){
+                input(bind:value=new_name, type="text", size="10", placeholder=t!("new_name"))
+                button(on:click=add_site){(t!("add_site"))}
+                button(on:click=add_field, disabled=(active_site.get().is_none())){(t!("add_field"))}
+            }
+            (if active_field.get().is_some() {
+                view! {
+                    div(class="select-input"){
+                        button(on:click=finish_job){(t!("finish_job"))}
+                        button(on:click=set_boundary){(t!("boundary_from_coverage"))}
+                        button(on:click=remove_field){(t!("remove_field"))}
+                    }
+                    h3{(t!("mowing_history"))}
+                    ul{(history())}
+                }
+            } else {
+                view! {}
+            })
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use nav_types::WGS84;
+use rust_i18n::t;
+use sycamore::prelude::*;
+
+use crate::{
+    guidance::{pass_spacing, AbLine, CurveLine, GuidanceLine, LineGuidance},
+    position::{use_sensor_store, FusedState},
+    projection::{GeoPoint, Projection},
+    units::{use_unit_system, Unit},
+};
+
+/// Number of segments on each side of the lightbar centre.
+const LIGHTBAR_SEGMENTS: i32 = 7;
+/// Cross track error represented by one lightbar segment in metres.
+const LIGHTBAR_RESOLUTION: f64 = 0.05;
+/// Heading errors below this many degrees are shown as straight ahead.
+const HEADING_DEADBAND: f64 = 3.0;
+/// Passes drawn on the map on each side of the current one.
+const SHOWN_PASSES: i32 = 3;
+
+#[component(inline_props)]
+pub fn Lightbar<G: Html>(guidance: ReadSignal<Option<LineGuidance>>) -> View<G> {
+    let system = use_unit_system();
+    let segments = View::new_fragment(
+        (-LIGHTBAR_SEGMENTS..=LIGHTBAR_SEGMENTS)
+            .map(|segment| {
+                let class = move || {
+                    let lit = guidance.with(|guidance| match guidance {
+                        Some(guidance) => {
+                            let error = (guidance.cross_track_error / LIGHTBAR_RESOLUTION)
+                                .round()
+                                .clamp(-LIGHTBAR_SEGMENTS as f64, LIGHTBAR_SEGMENTS as f64)
+                                as i32;
+                            // light towards the side the operator has to steer to
+                            (segment == 0 && error == 0)
+                                || (segment < 0 && segment >= -error) || (segment > 0 && segment <= -error)
+                        }
+                        None => false,
+                    });
+                    match (lit, segment) {
+                        (false, _) => "lightbar-segment",
+                        (true, 0) => "lightbar-segment on-line",
+                        (true, s) if s.abs() > LIGHTBAR_SEGMENTS / 2 => "lightbar-segment far-off",
+                        (true, _) => "lightbar-segment off-line",
+                    }
+                };
+                view! { div(class=(class())) }
+            })
+            .collect(),
+    );
+
+    view! {
+        div(class="lightbar"){
+            div(class="lightbar-segments"){(segments)}
+            div(class="lightbar-text"){
+                (guidance.with(|guidance| match guidance {
+                    Some(guidance) => format!(
+                        "{} {:.2} {}  {}  {} {}",
+                        if guidance.cross_track_error > 0.0 { "◀" } else { "▶" },
+                        Unit::Metre.in_system(system.get()).convert(guidance.cross_track_error.abs()),
+                        Unit::Metre.in_system(system.get()).symbol(),
+                        match guidance.heading_error {
+                            Some(error) if error > HEADING_DEADBAND => format!("↱ {:.0}°", error),
+                            Some(error) if error < -HEADING_DEADBAND => format!("↰ {:.0}°", -error),
+                            Some(_) => String::from("↑"),
+                            None => String::new(),
+                        },
+                        t!("pass"),
+                        guidance.pass
+                    ),
+                    None => t!("no_guidance"),
+                }))
+            }
+        }
+    }
+}
+
+#[component(inline_props)]
+pub fn Guidance<G: Html>(
+    guidance_line: Signal<Option<GuidanceLine>>,
+    projection: Signal<Projection>,
+    mower_width: ReadSignal<f64>,
+    overlap: Signal<f64>,
+    guide_lines: Signal<Vec<Vec<GeoPoint>>>,
+) -> View<G> {
+    let fused = use_sensor_store().fused;
+    let position = create_memo(move || {
+        fused.with(|fused| fused.position.map(|pos| GeoPoint::from(WGS84::from(pos))))
+    });
+    let point_a = create_signal(None::<GeoPoint>);
+    let recording = create_signal(None::<CurveLine>);
+
+    create_effect(move || {
+        if let Some(pos) = position.get() {
+            recording.update(|curve| {
+                if let Some(curve) = curve {
+                    let grid = projection
+                        .get_untracked()
+                        .grid_at(curve.points.first().unwrap_or(&pos));
+                    curve.record(&grid, pos);
+                }
+            });
+        }
+    });
+
+    let guidance = create_memo(move || {
+        guidance_line.with(|line| match (line, position.get()) {
+            (Some(line), Some(pos)) => line.grid(projection.get()).and_then(|grid| {
+                line.guidance(
+                    &grid,
+                    grid.project_point(&pos),
+                    fused.with(FusedState::heading).map(|heading| heading as f64),
+                    pass_spacing(mower_width.get(), overlap.get()),
+                )
+            }),
+            _ => None,
+        })
+    });
+
+    // contours around the current pass for the map, redrawn only when the pass changes
+    let current_pass = create_selector(move || guidance.with(|guidance| guidance.map_or(0, |g| g.pass)));
+    create_effect(move || {
+        let spacing = pass_spacing(mower_width.get(), overlap.get());
+        let pass = current_pass.get();
+        guide_lines.set(guidance_line.with(|line| {
+            line.as_ref()
+                .and_then(|line| {
+                    let grid = line.grid(projection.get())?;
+                    Some(line.contours(&grid, spacing, pass - SHOWN_PASSES..=pass + SHOWN_PASSES))
+                })
+                .unwrap_or_default()
+        }));
+    });
+
+    view! {
+        div(class="guidance"){
+            Lightbar(guidance=guidance)
+            div(class="guidance-buttons"){
+                button(class=(if point_a.with(Option::is_some) { "selected" } else { "" }), on:click=move |_| {
+                    point_a.set(position.get());
+                }){"A"}
+                button(on:click=move |_| {
+                    if let (Some(a), Some(b)) = (point_a.get(), position.get()) {
+                        guidance_line.set(Some(GuidanceLine::Ab(AbLine::new(a, b))));
+                        point_a.set(None);
+                    }
+                }){"B"}
+                button(class=(if recording.with(Option::is_some) { "selected" } else { "" }), title=t!("record_curve"), on:click=move |_| {
+                    if let Some(curve) = recording.get_clone() {
+                        if curve.points.len() > 1 {
+                            guidance_line.set(Some(GuidanceLine::Curve(curve)));
+                        }
+                        recording.set(None);
+                    } else {
+                        recording.set(Some(CurveLine::default()));
+                    }
+                }){"⤳"}
+                button(title=t!("clear_guidance"), on:click=move |_| {
+                    guidance_line.set(None);
+                    point_a.set(None);
+                    recording.set(None);
+                }){"✕"}
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use gloo_timers::future::TimeoutFuture;
+use rust_i18n::t;
+use sycamore::{futures::spawn_local_scoped, prelude::*};
+
+use crate::{
+    fields::format_duration,
+    geometry::{Coverage, PassAnalysis},
+    position::{use_sensor_store, MIN_HEADING_SPEED},
+    session::Session,
+    units::{use_unit_system, Unit},
+};
+
+#[component(inline_props)]
+pub fn HudValue<G: Html>(lable: String, value: ReadSignal<String>, unit: ReadSignal<String>) -> View<G> {
+    view! {
+        div(class="hud-value"){
+            span(class="hud-lable"){(lable)}
+            span(class="hud-number"){(value.get_clone())}
+            span(class="hud-unit"){(unit.get_clone())}
+        }
+    }
+}
+
+#[component(inline_props)]
+pub fn Hud<G: Html>(
+    session: ReadSignal<Session>,
+    coverage: ReadSignal<Option<Coverage>>,
+    analysis: ReadSignal<Option<PassAnalysis>>,
+) -> View<G> {
+    let raw = use_sensor_store().raw;
+    let system = use_unit_system();
+    let symbol = |unit: Unit| create_memo(move || String::from(unit.in_system(system.get()).symbol()));
+    let fixed = |symbol: &str| *create_signal(String::from(symbol));
+    let ground_speed = create_selector(move || raw.with(|raw| raw.velocity.norm() as f64));
+    let speed = create_memo(move || {
+        format!("{:.1}", Unit::KilometrePerHour.in_system(system.get()).convert(ground_speed.get()))
+    });
+    // keep the last heading while standing still
+    let last_heading = create_signal(String::from("-"));
+    create_effect(move || {
+        if ground_speed.get() > MIN_HEADING_SPEED as f64 {
+            let velocity = raw.with_untracked(|raw| raw.velocity);
+            let degrees = velocity[0].atan2(velocity[1]).to_degrees();
+            last_heading.set(format!("{:03.0}", (degrees + 360.0) % 360.0));
+        }
+    });
+    let accuracy = create_selector(move || {
+        raw.with(|raw| match raw.position {
+            Some(_) => format!("{:.1}", Unit::Metre.in_system(system.get()).convert(raw.accuracy as f64)),
+            None => String::from("-"),
+        })
+    });
+
+    // only the clock needs a timer, everything else follows its signals
+    let clock = create_signal(());
+    spawn_local_scoped(async move {
+        loop {
+            TimeoutFuture::new(1000).await;
+            clock.set(());
+        }
+    });
+    let distance = create_memo(move || {
+        let unit = Unit::Metre.in_system(system.get());
+        session.with(|session| format!("{:.0}", unit.convert(session.mowed_distance())))
+    });
+    let elapsed = create_memo(move || {
+        clock.track();
+        session.with(|session| format_duration(session.elapsed()))
+    });
+    let area = create_memo(move || {
+        let system = system.get();
+        coverage.with(|coverage| match coverage {
+            Some(coverage) => system.area(coverage.area()),
+            None => (String::from("-"), system.area_unit(0.0)),
+        })
+    });
+    let area_number = create_memo(move || area.with(|(number, _)| number.clone()));
+    let area_symbol = create_memo(move || area.with(|(_, unit)| String::from(unit.symbol())));
+    let overlap = create_memo(move || {
+        analysis.with(|analysis| match analysis {
+            Some(analysis) => format!("{:.1}", analysis.overlap_percentage()),
+            None => String::from("-"),
+        })
+    });
+
+    view! {
+        div(class="hud"){
+            HudValue(lable=t!("ground_speed"),value=speed,unit=symbol(Unit::KilometrePerHour))
+            HudValue(lable=t!("heading"),value=*last_heading,unit=fixed("°"))
+            HudValue(lable=t!("distance_mowed"),value=distance,unit=symbol(Unit::Metre))
+            HudValue(lable=t!("elapsed_time"),value=elapsed,unit=fixed(""))
+            HudValue(lable=t!("accuracy"),value=accuracy,unit=symbol(Unit::Metre))
+            HudValue(lable=t!("mowed_area"),value=area_number,unit=area_symbol)
+            HudValue(lable=t!("overlap"),value=overlap,unit=fixed("%"))
+        }
+    }
+}
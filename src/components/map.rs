@@ -0,0 +1,402 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use chrono::Utc;
+use geo::{LineString, MultiPolygon};
+use js_sys::{Array, Object, Reflect};
+use leaflet::{LatLng, Map, Polygon, Polyline, TileLayer};
+use nav_types::WGS84;
+use rust_i18n::t;
+use sycamore::prelude::*;
+use wasm_bindgen::{closure::Closure, JsValue};
+
+use crate::{
+    fields::{Field, LastMowed},
+    geometry::{Coverage, PassAnalysis},
+    position::use_sensor_store,
+    projection::{GeoPoint, GridSystem, ProjectedPoint},
+    tiles::{GeoBounds, TileServer, SUBDOMAIN},
+    utils::log_to_browser,
+};
+
+/// Leaflet release the `leaflet` crate binds to, bundled in `assets/leaflet`.
+const LEAFLET_VERSION: &str = "1.9.3";
+const FOLLOW_ZOOM: f64 = 19.0;
+
+const COVERAGE_COLOR: &str = "#00a000";
+const OVERLAP_COLOR: &str = "#e00000";
+const GAP_COLOR: &str = "#ff8c00";
+const BOUNDARY_COLOR: &str = "#0055a4";
+const GUIDE_COLOR: &str = "#ffffff";
+/// Areas not cut for this many days are shown in the full warning colour.
+const STALE_DAYS: f64 = 14.0;
+const MILLIS_PER_DAY: f64 = 86_400_000.0;
+
+fn options(entries: &[(&str, JsValue)]) -> JsValue {
+    let options = Object::new();
+    for (key, value) in entries {
+        let _ = Reflect::set(&options, &JsValue::from_str(key), value);
+    }
+    options.into()
+}
+
+/// Version of the Leaflet library loaded into the page, `None` if it is missing.
+fn leaflet_version() -> Option<String> {
+    let leaflet = Reflect::get(&js_sys::global(), &JsValue::from_str("L")).ok()?;
+    if leaflet.is_undefined() {
+        return None;
+    }
+    Reflect::get(&leaflet, &JsValue::from_str("version"))
+        .ok()?
+        .as_string()
+}
+
+fn path_options(color: &str, fill_opacity: f64) -> JsValue {
+    options(&[
+        ("color", JsValue::from_str(color)),
+        ("weight", JsValue::from_f64(1.0)),
+        ("fillOpacity", JsValue::from_f64(fill_opacity)),
+    ])
+}
+
+/// Nested Leaflet lat/lng arrays (polygons → rings → points) of a grid multipolygon.
+fn multipolygon_latlngs(grid: &GridSystem, polygons: &MultiPolygon<f64>) -> Array {
+    let ring_latlngs = |ring: &LineString<f64>| -> Array {
+        ring.coords()
+            .map(|coord| {
+                let (latitude, longitude) = grid.unproject(ProjectedPoint::new(coord.x, coord.y));
+                JsValue::from(LatLng::new(latitude, longitude))
+            })
+            .collect()
+    };
+    polygons
+        .iter()
+        .map(|polygon| {
+            let rings: Array = std::iter::once(polygon.exterior())
+                .chain(polygon.interiors())
+                .map(|ring| JsValue::from(ring_latlngs(ring)))
+                .collect();
+            JsValue::from(rings)
+        })
+        .collect()
+}
+
+/// Green for today over yellow to red for [`STALE_DAYS`] and older.
+fn age_color(days: f64) -> String {
+    let age = (days / STALE_DAYS).clamp(0.0, 1.0);
+    let (red, green) = if age < 0.5 {
+        (age * 2.0 * 255.0, 200.0)
+    } else {
+        (255.0, (1.0 - age) * 2.0 * 200.0)
+    };
+    format!("#{:02x}{:02x}00", red as u8, green as u8)
+}
+
+/// Replaces the layers in `layers` by the given grid multipolygons.
+fn redraw(
+    map: &Map,
+    layers: &mut Vec<Polygon>,
+    grid: &GridSystem,
+    polygons: &[(&MultiPolygon<f64>, &str, f64)],
+) {
+    for layer in layers.drain(..) {
+        layer.remove();
+    }
+    for (polygons, color, opacity) in polygons {
+        if polygons.0.is_empty() {
+            continue;
+        }
+        let layer = Polygon::new_with_options(
+            multipolygon_latlngs(grid, polygons),
+            &path_options(color, *opacity),
+        );
+        layer.addTo(map);
+        layers.push(layer);
+    }
+}
+
+#[component(inline_props)]
+pub fn MowMap<G: Html>(
+    coverage: ReadSignal<Option<Coverage>>,
+    analysis: ReadSignal<Option<PassAnalysis>>,
+    field: ReadSignal<Option<Field>>,
+    last_mowed: ReadSignal<Option<LastMowed>>,
+    view_bounds: Signal<Option<GeoBounds>>,
+    guide_lines: ReadSignal<Vec<Vec<GeoPoint>>>,
+    tile_server: ReadSignal<TileServer>,
+) -> View<G> {
+    let map: Rc<RefCell<Option<Map>>> = Rc::new(RefCell::new(None));
+    let map_ready = create_signal(false);
+    // shown over the map, a missing or different Leaflet must not go unnoticed
+    let leaflet_problem = create_signal(None::<String>);
+
+    {
+        let map = map.clone();
+        on_mount(move || {
+            match leaflet_version() {
+                None => {
+                    log_to_browser(String::from("Leaflet is not loaded, no map available"));
+                    leaflet_problem.set(Some(t!("leaflet_missing")));
+                    return;
+                }
+                Some(version) if version != LEAFLET_VERSION => {
+                    log_to_browser(format!(
+                        "Leaflet {} loaded, the map is built for {}",
+                        version, LEAFLET_VERSION
+                    ));
+                    leaflet_problem.set(Some(format!(
+                        "{} ({} ≠ {})",
+                        t!("leaflet_version_mismatch"),
+                        version,
+                        LEAFLET_VERSION
+                    )));
+                }
+                Some(_) => {}
+            }
+            let leaflet_map = Map::new("map", &JsValue::NULL);
+            leaflet_map.setView(&LatLng::new(0.0, 0.0), 2.0);
+            let moved_map = leaflet_map.clone();
+            let on_move = Closure::<dyn FnMut(JsValue)>::new(move |_| {
+                let bounds = moved_map.getBounds();
+                let (south_west, north_east) = (bounds.getSouthWest(), bounds.getNorthEast());
+                view_bounds.set(Some(GeoBounds {
+                    south: south_west.lat(),
+                    west: south_west.lng(),
+                    north: north_east.lat(),
+                    east: north_east.lng(),
+                }));
+            });
+            leaflet_map.on("moveend", on_move.as_ref());
+            // the map lives as long as the page
+            on_move.forget();
+            *map.borrow_mut() = Some(leaflet_map);
+            map_ready.set(true);
+        });
+    }
+
+    {
+        let map = map.clone();
+        let tiles: RefCell<Option<TileLayer>> = RefCell::new(None);
+        create_effect(move || {
+            if !map_ready.get() {
+                return;
+            }
+            if let Some(layer) = tiles.borrow_mut().take() {
+                layer.remove();
+            }
+            if let Some(map) = map.borrow().as_ref() {
+                tile_server.with(|server| {
+                    if server.url.is_empty() {
+                        return;
+                    }
+                    let layer = TileLayer::new(
+                        &server.url,
+                        &options(&[
+                            ("maxZoom", JsValue::from_f64(22.0)),
+                            ("maxNativeZoom", JsValue::from_f64(server.max_native_zoom as f64)),
+                            ("subdomains", JsValue::from_str(SUBDOMAIN)),
+                            ("attribution", JsValue::from_str(server.attribution)),
+                        ]),
+                    );
+                    layer.addTo(map);
+                    *tiles.borrow_mut() = Some(layer);
+                });
+            }
+        });
+    }
+
+    {
+        let map = map.clone();
+        let layers = RefCell::new(Vec::new());
+        create_effect(move || {
+            if !map_ready.get() {
+                return;
+            }
+            if let Some(map) = map.borrow().as_ref() {
+                coverage.with(|coverage| {
+                    if let Some(coverage) = coverage {
+                        redraw(
+                            map,
+                            &mut layers.borrow_mut(),
+                            &coverage.grid,
+                            &[(coverage.polygons(), COVERAGE_COLOR, 0.4)],
+                        );
+                    } else {
+                        // a finished job starts with an empty map
+                        for layer in layers.borrow_mut().drain(..) {
+                            layer.remove();
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    {
+        let map = map.clone();
+        let layers = RefCell::new(Vec::new());
+        create_effect(move || {
+            if !map_ready.get() {
+                return;
+            }
+            if let Some(map) = map.borrow().as_ref() {
+                analysis.with(|analysis| {
+                    if let Some(analysis) = analysis {
+                        redraw(
+                            map,
+                            &mut layers.borrow_mut(),
+                            &analysis.grid,
+                            &[
+                                (&analysis.overlaps, OVERLAP_COLOR, 0.6),
+                                (&analysis.gaps, GAP_COLOR, 0.8),
+                            ],
+                        );
+                    }
+                });
+            }
+        });
+    }
+
+    {
+        let map = map.clone();
+        let layers = RefCell::new(Vec::new());
+        create_effect(move || {
+            if !map_ready.get() {
+                return;
+            }
+            if let Some(map) = map.borrow().as_ref() {
+                last_mowed.with(|last_mowed| match last_mowed {
+                    Some(last_mowed) => {
+                        let now = Utc::now().timestamp_millis();
+                        let colors: Vec<String> = last_mowed
+                            .patches
+                            .iter()
+                            .map(|(time, _)| age_color((now - time) as f64 / MILLIS_PER_DAY))
+                            .collect();
+                        let polygons: Vec<_> = last_mowed
+                            .patches
+                            .iter()
+                            .zip(&colors)
+                            .map(|((_, patch), color)| (patch, color.as_str(), 0.5))
+                            .collect();
+                        redraw(map, &mut layers.borrow_mut(), &last_mowed.grid, &polygons);
+                    }
+                    None => {
+                        for layer in layers.borrow_mut().drain(..) {
+                            layer.remove();
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    {
+        let map = map.clone();
+        let boundary: RefCell<Option<Polygon>> = RefCell::new(None);
+        create_effect(move || {
+            if !map_ready.get() {
+                return;
+            }
+            if let Some(layer) = boundary.borrow_mut().take() {
+                layer.remove();
+            }
+            if let Some(map) = map.borrow().as_ref() {
+                field.with(|field| {
+                    if let Some(field) = field.as_ref().filter(|field| !field.boundary.is_empty()) {
+                        let latlngs: Array = field
+                            .boundary
+                            .iter()
+                            .map(|point| JsValue::from(LatLng::new(point.latitude, point.longitude)))
+                            .collect();
+                        let layer = Polygon::new_with_options(
+                            latlngs,
+                            &options(&[
+                                ("color", JsValue::from_str(BOUNDARY_COLOR)),
+                                ("weight", JsValue::from_f64(2.0)),
+                                ("dashArray", JsValue::from_str("6 4")),
+                                ("fill", JsValue::FALSE),
+                            ]),
+                        );
+                        layer.addTo(map);
+                        *boundary.borrow_mut() = Some(layer);
+                    }
+                });
+            }
+        });
+    }
+
+    {
+        let map = map.clone();
+        let layers: RefCell<Vec<Polyline>> = RefCell::new(Vec::new());
+        create_effect(move || {
+            if !map_ready.get() {
+                return;
+            }
+            for layer in layers.borrow_mut().drain(..) {
+                layer.remove();
+            }
+            if let Some(map) = map.borrow().as_ref() {
+                guide_lines.with(|lines| {
+                    for line in lines {
+                        let latlngs: Array = line
+                            .iter()
+                            .map(|point| JsValue::from(LatLng::new(point.latitude, point.longitude)))
+                            .collect();
+                        let layer = Polyline::new_with_options(
+                            latlngs,
+                            &options(&[
+                                ("color", JsValue::from_str(GUIDE_COLOR)),
+                                ("weight", JsValue::from_f64(2.0)),
+                                ("opacity", JsValue::from_f64(0.8)),
+                                ("dashArray", JsValue::from_str("8 6")),
+                                ("interactive", JsValue::FALSE),
+                            ]),
+                        );
+                        layer.addTo(map);
+                        layers.borrow_mut().push(layer);
+                    }
+                });
+            }
+        });
+    }
+
+    // centre once on the first fix, then the user pans freely
+    let fused = use_sensor_store().fused;
+    let centred = Cell::new(false);
+    create_effect(move || {
+        let position = fused.with(|fused| fused.position);
+        if !map_ready.get() || centred.get() {
+            return;
+        }
+        if let (Some(leaflet_map), Some(pos)) = (map.borrow().as_ref(), position) {
+            let wgs = WGS84::from(pos);
+            leaflet_map.setView(
+                &LatLng::new(wgs.latitude_degrees() as f64, wgs.longitude_degrees() as f64),
+                FOLLOW_ZOOM,
+            );
+            centred.set(true);
+        }
+    });
+
+    view! {
+        div(id="map")
+        (match leaflet_problem.get_clone() {
+            Some(problem) => view! { div(class="map-warning"){(problem)} },
+            None => view! {},
+        })
+        (if last_mowed.with(Option::is_some) {
+            view! {
+                div(class="age-legend"){
+                    span{(t!("last_mowed"))}
+                    span(class="age-scale"){}
+                    span{(format!("0 – {:.0} {}", STALE_DAYS, t!("days")))}
+                }
+            }
+        } else {
+            view! {}
+        })
+    }
+}
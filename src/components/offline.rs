@@ -0,0 +1,93 @@
+use rust_i18n::t;
+use sycamore::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::{
+    components::{SelectOption, ValueInput},
+    tiles::{
+        download, tile_count, tile_urls, GeoBounds, TileServer, TileSource, MAX_NATIVE_ZOOM, MAX_TILES,
+        MIN_OFFLINE_ZOOM,
+    },
+    utils::log_to_browser,
+};
+
+#[component(inline_props)]
+pub fn OfflineTiles<G: Html>(
+    view_bounds: ReadSignal<Option<GeoBounds>>,
+    max_zoom: Signal<f64>,
+    tile_server: ReadSignal<TileServer>,
+    tile_source: Signal<TileSource>,
+) -> View<G> {
+    let zoom = create_selector(move || max_zoom.get().max(0.0) as u32);
+    let count = create_selector(move || {
+        view_bounds
+            .get()
+            .map_or(0, |bounds| tile_server.with(|server| tile_count(server, bounds, zoom.get())))
+    });
+    let allowed = create_selector(move || tile_server.with(|server| server.offline));
+    let running = create_signal(false);
+    let status = create_signal(String::new());
+
+    let start = move |_| {
+        let Some(bounds) = view_bounds.get() else {
+            return;
+        };
+        if running.get() || !allowed.get() || count.get() == 0 || count.get() > MAX_TILES as u64 {
+            return;
+        }
+        let urls = tile_server.with(|server| tile_urls(server, bounds, zoom.get()));
+        running.set(true);
+        spawn_local(async move {
+            let total = urls.len();
+            let result = download(&urls, |done| status.set(format!("{} / {}", done, total))).await;
+            match result {
+                Ok(0) => status.set(t!("tiles_done")),
+                Ok(failed) => status.set(format!("{} {}", failed, t!("tiles_failed"))),
+                Err(err) => {
+                    log_to_browser(format!("Tile download failed: {:?}", err));
+                    status.set(t!("tiles_unavailable"));
+                }
+            }
+            running.set(false);
+        });
+    };
+
+    view! {
+            ValueInput(lable=t!("offline_max_zoom"),value=max_zoom,min=MIN_OFFLINE_ZOOM as f64,max=MAX_NATIVE_ZOOM as f64,step=1.0)
+            span{(t!("offline_tiles"))}
+            (if allowed.get() {
+                view! {
+                    button(on:click=start, disabled=(running.get() || count.get() == 0 || count.get() > MAX_TILES as u64)){
+                        (t!("download"))
+                    }
+                }
+            } else {
+                // the map shown is the one downloaded, so offer the sources that allow it
+                let sources = View::new_fragment(
+                    TileSource::all()
+                        .into_iter()
+                        .filter(|source| source.server("").offline)
+                        .map(|source| {
+                            view! {
+                                button(on:click=move |_| tile_source.set(source)){
+                                    (source.lable())
+                                }
+                            }
+                        })
+                        .collect(),
+                );
+                view! {
+                    div(class="select-input"){(sources)}
+                }
+            })
+            span{(if !allowed.get() {
+                t!("tiles_not_allowed")
+            } else if count.get() > MAX_TILES as u64 {
+                t!("area_too_large")
+            } else if status.with(String::is_empty) {
+                format!("{} {}", count.get(), t!("tiles"))
+            } else {
+                status.get_clone()
+            })}
+    }
+}
@@ -0,0 +1,107 @@
+use gloo_timers::future::TimeoutFuture;
+use rust_i18n::t;
+use sycamore::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::position::{
+    request_motion_permission, start_web_data, use_sensor_store, Sensor, SensorStatus,
+    SensorStatuses,
+};
+
+/// Time in milliseconds motion and orientation events get to arrive after starting.
+const SENSOR_TIMEOUT: u32 = 5000;
+
+fn status_lable(status: SensorStatus) -> String {
+    match status {
+        SensorStatus::Pending => t!("sensor_pending"),
+        SensorStatus::Granted => t!("sensor_granted"),
+        SensorStatus::Denied => t!("sensor_denied"),
+        SensorStatus::Unsupported => t!("sensor_unsupported"),
+    }
+}
+
+fn status_class(status: SensorStatus) -> &'static str {
+    match status {
+        SensorStatus::Pending => "sensor-pending",
+        SensorStatus::Granted => "sensor-granted",
+        SensorStatus::Denied => "sensor-denied",
+        SensorStatus::Unsupported => "sensor-unsupported",
+    }
+}
+
+#[component(inline_props)]
+fn SensorRow<G: Html>(lable: String, sensor: Sensor, status: ReadSignal<SensorStatuses>) -> View<G> {
+    view! {
+            span{(lable)}
+            span(class=(status_class(status.get().get(sensor)))){(status_lable(status.get().get(sensor)))}
+            div{}
+    }
+}
+
+/// Asks for the sensors from a user gesture, as iOS requires, and explains refusals.
+#[component(inline_props)]
+pub fn PermissionScreen<G: Html>(open: Signal<bool>) -> View<G> {
+    let sensors = use_sensor_store();
+    let status = sensors.status;
+    let started = create_signal(false);
+
+    create_effect(move || {
+        // nothing to explain once the position arrives
+        if status.with(|status| status.location == SensorStatus::Granted) {
+            open.set(false);
+        }
+    });
+
+    let start = move |_| {
+        if started.get() {
+            return;
+        }
+        started.set(true);
+        let answered = request_motion_permission(&sensors);
+        let sensors = sensors.clone();
+        spawn_local(async move {
+            answered.await;
+            start_web_data(&sensors);
+            TimeoutFuture::new(SENSOR_TIMEOUT).await;
+            sensors.mark_silent_sensors();
+        });
+    };
+
+    let help = move || {
+        let status = status.get();
+        let mut texts = Vec::new();
+        match status.location {
+            SensorStatus::Denied => texts.push(t!("location_denied_help")),
+            SensorStatus::Unsupported => texts.push(t!("location_unsupported_help")),
+            _ => {}
+        }
+        if status.motion == SensorStatus::Denied || status.orientation == SensorStatus::Denied {
+            texts.push(t!("motion_denied_help"));
+        }
+        if status.motion == SensorStatus::Unsupported || status.orientation == SensorStatus::Unsupported {
+            texts.push(t!("motion_unsupported_help"));
+        }
+        View::new_fragment(texts.into_iter().map(|text| view! { p{(text)} }).collect())
+    };
+
+    view! {
+        div(class="permission-screen"){
+            h2{(t!("permissions_title"))}
+            p{(t!("permissions_intro"))}
+            div(class="triple-column"){
+                SensorRow(lable=t!("sensor_location"),sensor=Sensor::Location,status=*status)
+                SensorRow(lable=t!("sensor_motion"),sensor=Sensor::Motion,status=*status)
+                SensorRow(lable=t!("sensor_orientation"),sensor=Sensor::Orientation,status=*status)
+            }
+            (help())
+            div(class="guidance-buttons"){
+                button(on:click=start, disabled=(started.get())){(t!("start_sensors"))}
+                (if started.get() {
+                    view! { button(on:click=move |_| open.set(false)){(t!("continue_anyway"))} }
+                } else {
+                    view! {}
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use rust_i18n::t;
+use sycamore::prelude::*;
+
+use crate::{
+    components::{SelectInput, ValueInput},
+    geometry::LeverArm,
+    profile::{EskfPreset, MowerProfile},
+    units::Unit,
+};
+
+#[component(inline_props)]
+pub fn ProfileEditor<G: Html>(profiles: Signal<Vec<MowerProfile>>, active: Signal<usize>) -> View<G> {
+    let name = create_signal(String::new());
+    let width = create_signal(0.0f64);
+    let offset_forward = create_signal(0.0f64);
+    let offset_lateral = create_signal(0.0f64);
+    let eskf_preset = create_signal(EskfPreset::default());
+    let typical_speed = create_signal(0.0f64);
+
+    // load the fields whenever another profile is picked or one is removed, without
+    // writing the half loaded profile back on the way
+    let loading = create_signal(false);
+    let profile_count = create_selector(move || profiles.with(Vec::len));
+    create_effect(move || {
+        let index = active.get();
+        profile_count.track();
+        if let Some(profile) = profiles.with_untracked(|profiles| profiles.get(index).cloned()) {
+            loading.set(true);
+            name.set(profile.name);
+            width.set(profile.width);
+            offset_forward.set(profile.lever_arm.forward);
+            offset_lateral.set(profile.lever_arm.lateral);
+            eskf_preset.set(profile.eskf_preset);
+            typical_speed.set(profile.typical_speed);
+            loading.set(false);
+        }
+    });
+
+    // and write every edit back
+    create_effect(move || {
+        let profile = MowerProfile {
+            name: name.get_clone(),
+            width: width.get(),
+            lever_arm: LeverArm::new(offset_forward.get(), offset_lateral.get()),
+            eskf_preset: eskf_preset.get(),
+            typical_speed: typical_speed.get(),
+        };
+        let index = active.get_untracked();
+        let changed = profiles.with_untracked(|profiles| profiles.get(index).is_some_and(|stored| *stored != profile));
+        if !loading.get_untracked() && changed {
+            profiles.update(|profiles| profiles[index] = profile);
+        }
+    });
+
+    view! {
+            span{(t!("mower_profile"))}
+            div(class="select-input"){
+                (View::new_fragment(profiles.with(|profiles| {
+                    profiles
+                        .iter()
+                        .enumerate()
+                        .map(|(index, profile)| {
+                            let profile_name = profile.name.clone();
+                            view! {
+                                button(class=(if active.get() == index { "selected" } else { "" }), on:click=move |_| active.set(index)){
+                                    (profile_name.clone())
+                                }
+                            }
+                        })
+                        .collect()
+                })))
+            }
+            div(class="select-input"){
+                button(title=t!("add_profile"), on:click=move |_| {
+                    profiles.update(|profiles| profiles.push(MowerProfile::new(t!("new_profile"))));
+                    active.set(profiles.with(Vec::len) - 1);
+                }){"+"}
+                button(title=t!("remove_profile"), on:click=move |_| {
+                    if profiles.with(Vec::len) > 1 {
+                        let index = active.get();
+                        profiles.update(|profiles| {
+                            profiles.remove(index);
+                        });
+                        active.set(index.min(profiles.with(Vec::len) - 1));
+                    }
+                }){"−"}
+            }
+            span{(t!("profile_name"))}
+            input(bind:value=name, type="text", size="8")
+            div{}
+            ValueInput(lable=t!("mower_width"),value=width,unit=Unit::Centimetre,min=0.1,max=20.0,step=0.01)
+            ValueInput(lable=t!("deck_offset_forward"),value=offset_forward,unit=Unit::Centimetre,min=-10.0,max=10.0,step=0.01)
+            ValueInput(lable=t!("deck_offset_lateral"),value=offset_lateral,unit=Unit::Centimetre,min=-10.0,max=10.0,step=0.01)
+            ValueInput(lable=t!("typical_speed"),value=typical_speed,unit=Unit::MetrePerSecond,min=0.1,max=20.0)
+            SelectInput(lable=t!("eskf_preset"),value=eskf_preset)
+    }
+}
@@ -1,64 +1,159 @@
+use chrono::Utc;
 use gloo_timers::future::TimeoutFuture;
 use nav_types::WGS84;
 use rust_i18n::t;
 use sycamore::{futures::spawn_local_scoped, prelude::*};
+use web_sys::PositionError;
 
 use crate::{
     components::{MenuButtons, ValueOutput},
-    position::get_raw_data, utils::log_to_browser,
+    mutex_box::failed_accesses,
+    position::{use_sensor_store, RawValues},
+    projection::Projection,
+    sensor_log::{Sample, SensorLog},
+    units::Unit,
+    utils::{download_file, log_to_browser},
 };
 
-#[component(inline_props)]
-pub fn RawValues<G: Html>(raw_visable: Signal<bool>, menu_visable: Signal<bool>) -> View<G> {
-    let altitude = create_signal(0.0f64);
-    let magnetic_declination = create_signal(0.0f64);
-    let latitude = create_signal(0.0f64);
-    let longitude = create_signal(0.0f64);
-    let speed_e = create_signal(0.0f64);
-    let speed_n = create_signal(0.0f64);
+fn wgs(raw: &RawValues) -> Option<WGS84<f32>> {
+    raw.position.map(WGS84::from)
+}
 
-    let acc_e = create_signal(0.0f64);
-    let acc_n = create_signal(0.0f64);
-    let acc_a = create_signal(0.0f64);
+fn position_error_text(code: u16) -> String {
+    match code {
+        PositionError::PERMISSION_DENIED => t!("position_error_denied"),
+        PositionError::POSITION_UNAVAILABLE => t!("position_error_unavailable"),
+        PositionError::TIMEOUT => t!("position_error_timeout"),
+        _ => code.to_string(),
+    }
+}
 
-    spawn_local_scoped(async move {
-        loop {
-            let data = get_raw_data();
+#[component(inline_props)]
+pub fn RawValues<G: Html>(
+    raw_visable: Signal<bool>,
+    menu_visable: Signal<bool>,
+    projection: Signal<Projection>,
+) -> View<G> {
+    let sensors = use_sensor_store();
+    let (raw, fused) = (sensors.raw, sensors.fused);
+    let value = move |read: fn(&RawValues) -> f64| create_selector(move || raw.with(read));
 
-            if let Some(pos) = data.position {
-                let wgs = WGS84::from(pos);
-                latitude.set(wgs.latitude_degrees() as f64);
-                longitude.set(wgs.longitude_degrees() as f64);
-                altitude.set(wgs.altitude() as f64);
-            }
-            magnetic_declination.set(data.magnetic_declination.to_degrees() as f64);
+    let grid_point = create_memo(move || {
+        fused.with(|fused| fused.position).map(|pos| {
+            let grid = projection.get().grid_for(WGS84::from(pos));
+            let point = grid.project_wgs(WGS84::from(pos));
+            (grid.name, point)
+        })
+    });
+    let grid_name = create_memo(move || {
+        grid_point.with(|grid_point| grid_point.as_ref().map_or(String::new(), |(name, _)| name.clone()))
+    });
+    let easting = create_selector(move || grid_point.with(|g| g.as_ref().map_or(0.0, |(_, p)| p.easting)));
+    let northing = create_selector(move || grid_point.with(|g| g.as_ref().map_or(0.0, |(_, p)| p.northing)));
 
-            speed_e.set(data.velocity[0] as f64);
-            speed_n.set(data.velocity[1] as f64);
+    let latitude = value(|raw| wgs(raw).map_or(0.0, |wgs| wgs.latitude_degrees() as f64));
+    let longitude = value(|raw| wgs(raw).map_or(0.0, |wgs| wgs.longitude_degrees() as f64));
+    let altitude = value(|raw| wgs(raw).map_or(0.0, |wgs| wgs.altitude() as f64));
+    let magnetic_declination = value(|raw| raw.magnetic_declination.to_degrees() as f64);
+    let speed_e = value(|raw| raw.velocity[0] as f64);
+    let speed_n = value(|raw| raw.velocity[1] as f64);
 
-            let acc=data.get_acceleration();
-            acc_a.set(acc[2] as f64);
-            acc_n.set(acc[0] as f64);
-            acc_e.set(acc[1] as f64);
-            log_to_browser(format!("Raw-Vals: {:?}",data));
+    let acc_a = value(|raw| raw.get_acceleration()[2] as f64);
+    let acc_n = value(|raw| raw.get_acceleration()[0] as f64);
+    let acc_e = value(|raw| raw.get_acceleration()[1] as f64);
+    let vibration = value(|raw| raw.vibration as f64);
+    let position_error = create_selector(move || {
+        raw.with(|raw| raw.position_error.map_or_else(String::new, position_error_text))
+    });
+
+    // the age of the last fix grows without new data and the access counter is no
+    // signal, so they need a clock
+    let clock = create_signal(());
+    spawn_local_scoped(async move {
+        loop {
             TimeoutFuture::new(1000).await;
+            clock.set(());
         }
     });
+    let failed_accesses = create_memo(move || {
+        clock.track();
+        failed_accesses() as f64
+    });
+    let fix_rate = create_memo(move || {
+        clock.track();
+        raw.with(|raw| raw.fix_rate(Utc::now().timestamp_millis()) as f64)
+    });
+    let fix_age = create_memo(move || {
+        clock.track();
+        raw.with(|raw| raw.last_fix)
+            .map_or(0.0, |last_fix| (Utc::now().timestamp_millis() - last_fix) as f64 / 1000.0)
+    });
+
+    // every sensor update while logging, for analysing the filter offline
+    let logging = create_signal(false);
+    let log = create_signal(SensorLog::default());
+    let log_length = create_selector(move || log.with(SensorLog::len));
+    let export_status = create_signal(String::new());
+    create_effect(move || {
+        raw.with(|raw| {
+            if logging.get_untracked() {
+                let sample = fused.with_untracked(|fused| Sample::new(raw, fused));
+                log.update(|log| log.push(sample));
+            }
+        })
+    });
+    let toggle_logging = move |_| {
+        if !logging.get() {
+            // a new recording replaces the previous one
+            log.update(SensorLog::clear);
+        }
+        logging.set(!logging.get());
+    };
+    let export = move |_| {
+        let file_name = format!("sensor-log-{}.csv", Utc::now().format("%Y%m%d-%H%M%S"));
+        match download_file(&file_name, "text/csv", &log.with(|log| log.to_csv(projection.get()))) {
+            Ok(()) => export_status.set(String::new()),
+            Err(err) => {
+                log_to_browser(format!("Sensor log export failed: {:?}", err));
+                export_status.set(t!("sensor_log_export_failed"));
+            }
+        }
+    };
 
     view! {
         div(class="overlay"){
             MenuButtons(raw_visable=raw_visable,menu_visable=menu_visable)
             br{}
             div(class="triple-column"){
-        ValueOutput(lable=t!("longitude"),value=*longitude){""}
-        ValueOutput(lable=t!("latitude"),value=*latitude){""}
-        ValueOutput(lable=t!("altitude"),value=*altitude){"m"}
-        ValueOutput(lable=t!("magnetic_declination"),value=*magnetic_declination){(t!("degree"))}
-        ValueOutput(lable=t!("speed east"),value=*speed_e){"m/s"}
-        ValueOutput(lable=t!("speed_north"),value=*speed_n){"m/s"}
-        ValueOutput(lable=t!("acceleration east"),value=*acc_e){"m/s²"}
-        ValueOutput(lable=t!("acceleration_north"),value=*acc_n){"m/s²"}
-        ValueOutput(lable=t!("acceleration altitude"),value=*acc_a){"m/s²"}
+        span{(t!("grid"))}
+        span{(grid_name.get_clone())}
+        div{}
+        span{(t!("sensor_log"))}
+        div(class="select-input"){
+            button(on:click=toggle_logging){
+                (if logging.get() { t!("sensor_log_stop") } else { t!("sensor_log_start") })
+            }
+            button(on:click=export, disabled=(log_length.get() == 0)){(t!("sensor_log_export"))}
+        }
+        span{(format!("{} {} {}", log_length.get(), t!("samples"), export_status.get_clone()))}
+        ValueOutput(lable=t!("easting"),value=easting,unit=Unit::Metre){}
+        ValueOutput(lable=t!("northing"),value=northing,unit=Unit::Metre){}
+        ValueOutput(lable=t!("longitude"),value=longitude){}
+        ValueOutput(lable=t!("latitude"),value=latitude){}
+        ValueOutput(lable=t!("altitude"),value=altitude,unit=Unit::Metre){}
+        ValueOutput(lable=t!("magnetic_declination"),value=magnetic_declination){(t!("degree"))}
+        ValueOutput(lable=t!("speed_east"),value=speed_e,unit=Unit::MetrePerSecond){}
+        ValueOutput(lable=t!("speed_north"),value=speed_n,unit=Unit::MetrePerSecond){}
+        ValueOutput(lable=t!("acceleration_east"),value=acc_e,unit=Unit::MetrePerSecondSquared){}
+        ValueOutput(lable=t!("acceleration_north"),value=acc_n,unit=Unit::MetrePerSecondSquared){}
+        ValueOutput(lable=t!("acceleration_vertical"),value=acc_a,unit=Unit::MetrePerSecondSquared){}
+        ValueOutput(lable=t!("vibration"),value=vibration,unit=Unit::MetrePerSecondSquared){}
+        ValueOutput(lable=t!("fix_rate"),value=fix_rate){"Hz"}
+        ValueOutput(lable=t!("fix_age"),value=fix_age,unit=Unit::Second){}
+        ValueOutput(lable=t!("failed_accesses"),value=failed_accesses){}
+        span{(t!("position_error"))}
+        span{(position_error.get_clone())}
+        div{}
 
             }
     }}
@@ -0,0 +1,76 @@
+use rust_i18n::t;
+use sycamore::prelude::*;
+
+use crate::{
+    geometry::PassAnalysis,
+    report::{report_html, thumbnail_svg, SessionSummary},
+    session::Session,
+    units::use_unit_system,
+    utils::{log_to_browser, open_html},
+};
+
+/// Longer side of the coverage drawing in pixels.
+const THUMBNAIL_SIZE: f64 = 240.0;
+
+#[component(inline_props)]
+pub fn SessionReport<G: Html>(
+    session: ReadSignal<Session>,
+    analysis: ReadSignal<Option<PassAnalysis>>,
+    mower_width: ReadSignal<f64>,
+) -> View<G> {
+    let system = use_unit_system();
+    let open = create_signal(false);
+
+    // only worked out while shown, the session changes with every point
+    let rows = create_memo(move || {
+        if !open.get() {
+            return Vec::new();
+        }
+        let summary = session.with(|session| {
+            analysis.with(|analysis| SessionSummary::new(session, analysis.as_ref(), mower_width.get()))
+        });
+        summary.rows(system.get())
+    });
+    let thumbnail = create_memo(move || {
+        if !open.get() {
+            return String::new();
+        }
+        analysis.with(|analysis| match analysis {
+            Some(analysis) => session
+                .with(|session| thumbnail_svg(&analysis.grid, &analysis.coverage, &session.points, &[], THUMBNAIL_SIZE)),
+            None => String::new(),
+        })
+    });
+
+    let print = move |_| {
+        let html = rows.with(|rows| thumbnail.with(|thumbnail| report_html(&t!("session_report"), rows, thumbnail)));
+        if let Err(err) = open_html(&html) {
+            log_to_browser(format!("Opening the report failed: {:?}", err));
+        }
+    };
+
+    view! {
+        div(class="session-report"){
+            h3{
+                button(on:click=move |_| open.set(!open.get())){(t!("session_report"))}
+            }
+            (if open.get() {
+                let table = View::new_fragment(rows.with(|rows| {
+                    rows.iter()
+                        .map(|(lable, value)| {
+                            let (lable, value) = (lable.clone(), value.clone());
+                            view! { tr{ th{(lable)} td{(value)} } }
+                        })
+                        .collect()
+                }));
+                view! {
+                    table{(table)}
+                    div(class="report-thumbnail", dangerously_set_inner_html=thumbnail.get_clone())
+                    button(on:click=print){(t!("report_print"))}
+                }
+            } else {
+                view! {}
+            })
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use rust_i18n::t;
+use sycamore::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{Event, HtmlInputElement};
+
+use crate::{
+    settings::{Settings, SettingsSignals},
+    utils::{download_file, log_to_browser},
+};
+
+const EXPORT_FILE_NAME: &str = "grass-mapper-settings.json";
+
+/// Export and import of all settings, to set up the other phones of a crew the same way.
+#[component(inline_props)]
+pub fn SettingsTransfer<G: Html>(settings: SettingsSignals) -> View<G> {
+    let status = create_signal(String::new());
+
+    let export = move |_| {
+        let json = settings.get().to_json();
+        match download_file(EXPORT_FILE_NAME, "application/json", &json) {
+            Ok(()) => status.set(String::new()),
+            Err(err) => {
+                log_to_browser(format!("Settings export failed: {:?}", err));
+                status.set(t!("settings_export_failed"));
+            }
+        }
+    };
+
+    let import = move |event: Event| {
+        let Some(input) = event.target().and_then(|target| target.dyn_into::<HtmlInputElement>().ok()) else {
+            return;
+        };
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+        // the same file can be picked again after editing it
+        input.set_value("");
+        spawn_local(async move {
+            let text = JsFuture::from(file.text()).await.ok().and_then(|text| text.as_string());
+            match Settings::from_json(&text.unwrap_or_default()) {
+                Ok(mut imported) => {
+                    // field ids belong to the database of this phone, the operator to it
+                    imported.active_field = settings.active_field.get_untracked();
+                    imported.operator = settings.operator.get_clone_untracked();
+                    settings.set(imported);
+                    status.set(t!("settings_imported"));
+                }
+                Err(err) => {
+                    log_to_browser(format!("Settings import failed: {}", err));
+                    status.set(t!("settings_import_failed"));
+                }
+            }
+        });
+    };
+
+    view! {
+            span{(t!("settings_transfer"))}
+            div(class="select-input"){
+                button(on:click=export){(t!("settings_export"))}
+                label(class="file-button"){
+                    (t!("settings_import"))
+                    input(type="file", accept=".json,application/json", on:change=import)
+                }
+            }
+            span{(status.get_clone())}
+    }
+}
@@ -0,0 +1,112 @@
+use js_sys::{Array, Function, Promise};
+use serde::{de::DeserializeOwned, Serialize};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{window, Event, IdbDatabase, IdbOpenDbRequest, IdbRequest, IdbTransactionMode};
+
+use crate::utils::log_to_browser;
+
+const DB_NAME: &str = "grass-mapper";
+const DB_VERSION: u32 = 1;
+
+pub const SITES: &str = "sites";
+pub const FIELDS: &str = "fields";
+pub const JOBS: &str = "jobs";
+
+/// Resolves with the result of `request` once it succeeded.
+async fn finished(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = Promise::new(&mut |resolve: Function, reject: Function| {
+        let target = request.clone();
+        let on_success = Closure::once_into_js(move |_: Event| {
+            let _ = resolve.call1(&JsValue::NULL, &target.result().unwrap_or(JsValue::UNDEFINED));
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+
+        let target = request.clone();
+        let on_error = Closure::once_into_js(move |_: Event| {
+            let error = target.error().ok().flatten().map(JsValue::from);
+            let _ = reject.call1(&JsValue::NULL, &error.unwrap_or(JsValue::UNDEFINED));
+        });
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    JsFuture::from(promise).await
+}
+
+/// Opens the database, creating the object stores on first use.
+async fn open() -> Result<IdbDatabase, JsValue> {
+    let factory = window()
+        .ok_or(JsValue::from_str("no window"))?
+        .indexed_db()?
+        .ok_or(JsValue::from_str("IndexedDB not available"))?;
+    let request = factory.open_with_u32(DB_NAME, DB_VERSION)?;
+
+    let upgrading = request.clone();
+    let on_upgrade = Closure::once_into_js(move |_: Event| {
+        if let Ok(db) = upgrading.result() {
+            let db: IdbDatabase = db.unchecked_into();
+            for store in [SITES, FIELDS, JOBS] {
+                if !db.object_store_names().contains(store) {
+                    let _ = db.create_object_store(store);
+                }
+            }
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+
+    let request: &IdbOpenDbRequest = &request;
+    Ok(finished(request).await?.unchecked_into())
+}
+
+/// Stores `value` as JSON under `id`, replacing an older version.
+pub async fn put<T: Serialize>(store: &str, id: i64, value: &T) -> Result<(), JsValue> {
+    let json = serde_json::to_string(value).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let db = open().await?;
+    let request = db
+        .transaction_with_str_and_mode(store, IdbTransactionMode::Readwrite)?
+        .object_store(store)?
+        .put_with_key(&JsValue::from_str(&json), &JsValue::from_f64(id as f64))?;
+    finished(&request).await?;
+    db.close();
+    Ok(())
+}
+
+/// All values of `store`, entries that no longer parse are skipped.
+pub async fn get_all<T: DeserializeOwned>(store: &str) -> Result<Vec<T>, JsValue> {
+    let db = open().await?;
+    let request = db.transaction_with_str(store)?.object_store(store)?.get_all()?;
+    let values: Array = finished(&request).await?.unchecked_into();
+    db.close();
+    Ok(values
+        .iter()
+        .filter_map(|value| value.as_string())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect())
+}
+
+pub async fn delete(store: &str, id: i64) -> Result<(), JsValue> {
+    let db = open().await?;
+    let request = db
+        .transaction_with_str_and_mode(store, IdbTransactionMode::Readwrite)?
+        .object_store(store)?
+        .delete(&JsValue::from_f64(id as f64))?;
+    finished(&request).await?;
+    db.close();
+    Ok(())
+}
+
+/// Stores `value` in the background, failures are only logged.
+pub fn save<T: Serialize + 'static>(store: &'static str, id: i64, value: T) {
+    spawn_local(async move {
+        if let Err(err) = put(store, id, &value).await {
+            log_to_browser(format!("Saving to {} failed: {:?}", store, err));
+        }
+    });
+}
+
+/// All values of `store`, empty if the database cannot be read.
+pub async fn load_all<T: DeserializeOwned>(store: &str) -> Vec<T> {
+    get_all(store).await.unwrap_or_else(|err| {
+        log_to_browser(format!("Loading {} failed: {:?}", store, err));
+        Vec::new()
+    })
+}
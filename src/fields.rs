@@ -0,0 +1,250 @@
+use chrono::{Local, TimeZone, Utc};
+use geo::{Area, BooleanOps, MultiPolygon};
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{from_geo_polygons, to_geo_polygons, Coverage, GeoPolygons};
+use crate::projection::{polygon_area, GeoPoint, GridSystem, ProjectedPoint, Projection};
+use crate::session::Session;
+
+/// Ids are the creation time in unix milliseconds, unique enough for one device.
+pub fn new_id() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+/// Local date and time of a unix time in milliseconds.
+pub fn format_time(time: i64) -> String {
+    Local
+        .timestamp_millis_opt(time)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Seconds as hours, minutes and seconds.
+pub fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds / 60) % 60,
+        seconds % 60
+    )
+}
+
+/// A property of the customer, e.g. a park or a sports ground.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Site {
+    pub id: i64,
+    pub name: String,
+}
+
+impl Site {
+    pub fn new(name: String) -> Self {
+        Site { id: new_id(), name }
+    }
+}
+
+/// An area of a site that is mowed as a whole.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Field {
+    pub id: i64,
+    pub site: i64,
+    pub name: String,
+    /// outline of the field, empty until one has been set
+    pub boundary: Vec<GeoPoint>,
+}
+
+impl Field {
+    pub fn new(site: i64, name: String) -> Self {
+        Field {
+            id: new_id(),
+            site,
+            name,
+            boundary: Vec::new(),
+        }
+    }
+
+    /// Area inside the boundary in m², measured in `projection`.
+    pub fn area(&self, projection: Projection) -> f64 {
+        let Some(first) = self.boundary.first() else {
+            return 0.0;
+        };
+        let grid = projection.grid_at(first);
+        let points: Vec<_> = self.boundary.iter().map(|point| grid.project_point(point)).collect();
+        let scale = grid.scale_factor(points[0]);
+        polygon_area(&points) / (scale * scale)
+    }
+
+    /// Takes the outline of the largest mowed patch as the boundary.
+    pub fn set_boundary_from(&mut self, coverage: &Coverage) {
+        let largest = coverage
+            .polygons()
+            .iter()
+            .max_by(|a, b| a.unsigned_area().total_cmp(&b.unsigned_area()));
+        if let Some(polygon) = largest {
+            self.boundary = polygon
+                .exterior()
+                .coords()
+                .map(|coord| {
+                    let (latitude, longitude) = coverage
+                        .grid
+                        .unproject(ProjectedPoint::new(coord.x, coord.y));
+                    GeoPoint { latitude, longitude }
+                })
+                .collect();
+        }
+    }
+}
+
+/// One mowing of a field, the recorded session and what it covered.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: i64,
+    pub field: i64,
+    pub session: Session,
+    pub coverage: GeoPolygons,
+    /// mowed area in m²
+    pub area: f64,
+    /// who mowed, shown in the report for the customer
+    #[serde(default)]
+    pub operator: String,
+}
+
+impl Job {
+    pub fn new(field: i64, session: Session, coverage: Option<&Coverage>, operator: String) -> Self {
+        Job {
+            id: new_id(),
+            field,
+            session,
+            coverage: coverage
+                .map(|coverage| to_geo_polygons(&coverage.grid, coverage.polygons()))
+                .unwrap_or_default(),
+            area: coverage.map_or(0.0, Coverage::area),
+            operator,
+        }
+    }
+
+    /// Unix time in milliseconds the job ended.
+    pub fn finished(&self) -> i64 {
+        self.session
+            .last_point()
+            .map_or(self.session.started, |point| point.time)
+    }
+}
+
+/// Jobs of `field`, latest first.
+pub fn field_history(jobs: &[Job], field: i64) -> Vec<&Job> {
+    let mut history: Vec<&Job> = jobs.iter().filter(|job| job.field == field).collect();
+    history.sort_by_key(|job| std::cmp::Reverse(job.finished()));
+    history
+}
+
+/// Unix time in milliseconds `field` was last cut.
+pub fn last_cut(jobs: &[Job], field: i64) -> Option<i64> {
+    field_history(jobs, field).first().map(|job| job.finished())
+}
+
+/// The parts of a field by the time they were last cut.
+#[derive(Clone, Debug)]
+pub struct LastMowed {
+    pub grid: GridSystem,
+    /// unix time in milliseconds of the last cut and the area cut then, latest first
+    pub patches: Vec<(i64, MultiPolygon<f64>)>,
+}
+
+impl LastMowed {
+    /// Splits the coverage of `history`, latest job first, so that every patch keeps
+    /// only the time of its latest cut.
+    pub fn new(projection: Projection, history: &[&Job]) -> Option<Self> {
+        let first = history
+            .iter()
+            .flat_map(|job| job.coverage.iter().flatten().flatten())
+            .next()?;
+        let grid = projection.grid_at(first);
+        let mut covered = MultiPolygon::new(vec![]);
+        let mut patches = Vec::new();
+        for job in history {
+            let mowed = from_geo_polygons(&grid, &job.coverage);
+            let fresh = mowed.difference(&covered);
+            if !fresh.0.is_empty() {
+                patches.push((job.finished(), fresh));
+            }
+            covered = covered.union(&mowed);
+        }
+        Some(LastMowed { grid, patches })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{assert_close, grid, grid_point, swath};
+
+    /// Job of `field` finished at `finished`, mowing a `width` wide strip 10 m northwards.
+    fn job(field: i64, finished: i64, east: f64, width: f64) -> Job {
+        let mut coverage = Coverage::new(grid());
+        coverage.add(swath(east, 0.0, width));
+        coverage.add(swath(east, 10.0, width));
+        let session = Session {
+            started: finished,
+            points: Vec::new(),
+            profile: None,
+            gaps: Vec::new(),
+        };
+        Job::new(field, session, Some(&coverage), String::new())
+    }
+
+    #[test]
+    fn field_area() {
+        let mut field = Field::new(1, String::from("Meadow"));
+        assert_eq!(field.area(Projection::Utm), 0.0);
+        let grid = grid();
+        field.boundary = [(0.0, 0.0), (100.0, 0.0), (100.0, 50.0), (0.0, 50.0)]
+            .into_iter()
+            .map(|(east, north)| {
+                let (latitude, longitude) = grid.unproject(grid_point(east, north));
+                GeoPoint { latitude, longitude }
+            })
+            .collect();
+        // the grid shrinks the ground by 0.9996 on its central meridian
+        assert_close(field.area(Projection::Utm), 5000.0 / (0.9996 * 0.9996), 0.01);
+        // another ellipsoid and grid measure the same area on the ground
+        assert_close(field.area(Projection::GaussKrueger), field.area(Projection::Utm), 0.1);
+    }
+
+    #[test]
+    fn history_latest_first() {
+        let jobs = vec![job(1, 100, 0.0, 1.0), job(2, 300, 0.0, 1.0), job(1, 200, 0.0, 1.0)];
+        let history = field_history(&jobs, 1);
+        assert_eq!(
+            history.iter().map(|job| job.finished()).collect::<Vec<_>>(),
+            vec![200, 100]
+        );
+        assert_eq!(last_cut(&jobs, 1), Some(200));
+        assert_eq!(last_cut(&jobs, 2), Some(300));
+        assert_eq!(last_cut(&jobs, 3), None);
+    }
+
+    #[test]
+    fn last_mowed_patches() {
+        // strips from 0 to 2 m, 0 to 4 m and 2 to 6 m east, the latest keeps its 4 m,
+        // the one before only its western 2 m and the oldest is cut over completely
+        let jobs = vec![
+            job(1, 100, 1.0, 2.0),
+            job(1, 200, 2.0, 4.0),
+            job(1, 300, 4.0, 4.0),
+        ];
+        let history = field_history(&jobs, 1);
+        let last_mowed = LastMowed::new(Projection::Utm, &history).unwrap();
+        let patches: Vec<(i64, f64)> = last_mowed
+            .patches
+            .iter()
+            .map(|(time, patch)| (*time, patch.unsigned_area()))
+            .collect();
+        assert_eq!(patches.len(), 2);
+        assert_eq!((patches[0].0, patches[1].0), (300, 200));
+        assert_close(patches[0].1, 40.0, 1e-3);
+        assert_close(patches[1].1, 20.0, 1e-3);
+        assert!(LastMowed::new(Projection::Utm, &[]).is_none());
+    }
+}
@@ -0,0 +1,681 @@
+use geo::{Area, BooleanOps, Centroid, Coord, Intersects, LineString, MultiPolygon, Polygon};
+use serde::{Deserialize, Serialize};
+
+use crate::projection::{GeoPoint, GridSystem, ProjectedPoint};
+use crate::session::TrackPoint;
+
+/// A pass ends when the direction of travel deviates this much from its start in degrees.
+const PASS_TURN_ANGLE: f64 = 60.0;
+/// Direction changes above this in degrees are reversing, the deck does not sweep round.
+const REVERSE_ANGLE: f64 = 150.0;
+/// Without a direction of travel for this long in milliseconds the vehicle stood still.
+const STANDSTILL_TIME: i64 = 2000;
+/// Direction changes below this in degrees need no turn wedge.
+const MIN_TURN_ANGLE: f64 = 1.0;
+/// Angle covered by one edge of the arc of a turn wedge in degrees.
+const WEDGE_STEP: f64 = 15.0;
+/// Segments shorter than this in metres are skipped, their direction is just noise.
+const MIN_SEGMENT_LENGTH: f64 = 0.05;
+/// Gaps smaller than this in m² are treated as numerical noise.
+const MIN_GAP_AREA: f64 = 0.05;
+
+/// Position of the deck centre and the cut width there.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SwathPoint {
+    pub point: ProjectedPoint,
+    pub width: f64,
+}
+
+impl SwathPoint {
+    pub fn new(point: ProjectedPoint, width: f64) -> Self {
+        SwathPoint { point, width }
+    }
+}
+
+/// Position of the deck centre relative to the phone, in metres.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LeverArm {
+    /// positive when the deck is ahead of the phone
+    pub forward: f64,
+    /// positive when the deck is right of the phone
+    pub lateral: f64,
+}
+
+impl LeverArm {
+    pub fn new(forward: f64, lateral: f64) -> Self {
+        LeverArm { forward, lateral }
+    }
+
+    /// Deck centre for the phone at `antenna` travelling towards `heading`, in radians
+    /// clockwise from north.
+    pub fn apply(&self, antenna: ProjectedPoint, heading: f64) -> ProjectedPoint {
+        let (sin, cos) = heading.sin_cos();
+        ProjectedPoint::new(
+            antenna.easting + self.forward * sin + self.lateral * cos,
+            antenna.northing + self.forward * cos - self.lateral * sin,
+        )
+    }
+}
+
+/// Direction the vehicle faces, kept apart from the direction of travel so that
+/// reversing does not turn the deck offset round.
+///
+/// The direction of travel flips abruptly when the mower backs up, while turns change
+/// it gradually. Each flip toggles between driving forwards and reversing. A flip after
+/// standing still is a turn on the spot, so the first direction after a standstill is
+/// taken as forwards, like the very first one.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VehicleHeading {
+    heading: Option<f64>,
+    reversing: bool,
+    /// time of the last update in milliseconds since the epoch
+    updated: i64,
+}
+
+impl VehicleHeading {
+    /// Updates with the direction of travel in radians clockwise from north at `time` in
+    /// milliseconds since the epoch. Updates only arrive while the vehicle moves.
+    pub fn update(&mut self, direction: f64, time: i64) {
+        let facing = |reversing: bool| if reversing { direction + std::f64::consts::PI } else { direction };
+        if time - self.updated > STANDSTILL_TIME {
+            self.reversing = false;
+        } else if let Some(heading) = self.heading {
+            if turn_angle(heading, facing(self.reversing)).abs() > REVERSE_ANGLE.to_radians() {
+                self.reversing = !self.reversing;
+            }
+        }
+        self.heading = Some(turn_angle(0.0, facing(self.reversing)));
+        self.updated = time;
+    }
+
+    /// Direction the vehicle faces in radians clockwise from north, the last known one
+    /// while standing still.
+    pub fn heading(&self) -> Option<f64> {
+        self.heading
+    }
+}
+
+/// Direction from `a` to `b` in radians clockwise from north.
+fn bearing(a: ProjectedPoint, b: ProjectedPoint) -> f64 {
+    (b.easting - a.easting).atan2(b.northing - a.northing)
+}
+
+/// Signed difference `to - from` in radians, wrapped to ±π.
+fn turn_angle(from: f64, to: f64) -> f64 {
+    let full = std::f64::consts::TAU;
+    (to - from + full / 2.0).rem_euclid(full) - full / 2.0
+}
+
+/// The area covered by the deck moving from `a` to `b`, the width may change on the way.
+pub fn segment_swath(a: SwathPoint, b: SwathPoint) -> Option<Polygon<f64>> {
+    let length = a.point.distance(&b.point);
+    if length < f64::EPSILON || (a.width <= 0.0 && b.width <= 0.0) {
+        return None;
+    }
+    // right hand normal of the direction of travel
+    let normal_e = (b.point.northing - a.point.northing) / length;
+    let normal_n = -(b.point.easting - a.point.easting) / length;
+    let (ha, hb) = (a.width.max(0.0) / 2.0, b.width.max(0.0) / 2.0);
+    let (a, b) = (a.point, b.point);
+    Some(Polygon::new(
+        LineString::from(vec![
+            (a.easting - normal_e * ha, a.northing - normal_n * ha),
+            (b.easting - normal_e * hb, b.northing - normal_n * hb),
+            (b.easting + normal_e * hb, b.northing + normal_n * hb),
+            (a.easting + normal_e * ha, a.northing + normal_n * ha),
+        ]),
+        vec![],
+    ))
+}
+
+/// Fan filling the wedge left open on the outside of a turn at `center`.
+///
+/// Returns `None` for straight travel and for reversing, where the deck does not sweep.
+pub fn turn_wedge(center: SwathPoint, direction_in: f64, direction_out: f64) -> Option<Polygon<f64>> {
+    let turn = turn_angle(direction_in, direction_out);
+    if center.width <= 0.0
+        || turn.abs() < MIN_TURN_ANGLE.to_radians()
+        || turn.abs() > REVERSE_ANGLE.to_radians()
+    {
+        return None;
+    }
+    // the outside of a right turn is on the left and vice versa
+    let start = direction_in - turn.signum() * std::f64::consts::FRAC_PI_2;
+    let steps = (turn.abs() / WEDGE_STEP.to_radians()).ceil() as usize;
+    let radius = center.width / 2.0;
+    let c = center.point;
+    let mut ring = vec![Coord {
+        x: c.easting,
+        y: c.northing,
+    }];
+    ring.extend((0..=steps).map(|i| {
+        let angle = start + turn * i as f64 / steps as f64;
+        Coord {
+            x: c.easting + radius * angle.sin(),
+            y: c.northing + radius * angle.cos(),
+        }
+    }));
+    Some(Polygon::new(LineString::from(ring), vec![]))
+}
+
+/// Area of grid polygons on the ground in m², corrected for the grid's scale factor.
+pub fn ground_area(grid: &GridSystem, polygons: &MultiPolygon<f64>) -> f64 {
+    let area = polygons.unsigned_area();
+    match polygons.centroid() {
+        Some(centroid) => {
+            let scale = grid.scale_factor(ProjectedPoint::new(centroid.x(), centroid.y()));
+            area / (scale * scale)
+        }
+        None => area,
+    }
+}
+
+/// WGS84 polygons → rings → points, the storable form of grid polygons.
+pub type GeoPolygons = Vec<Vec<Vec<GeoPoint>>>;
+
+pub fn to_geo_polygons(grid: &GridSystem, polygons: &MultiPolygon<f64>) -> GeoPolygons {
+    let ring = |ring: &LineString<f64>| -> Vec<GeoPoint> {
+        ring.coords()
+            .map(|coord| {
+                let (latitude, longitude) = grid.unproject(ProjectedPoint::new(coord.x, coord.y));
+                GeoPoint { latitude, longitude }
+            })
+            .collect()
+    };
+    polygons
+        .iter()
+        .map(|polygon| {
+            std::iter::once(polygon.exterior())
+                .chain(polygon.interiors())
+                .map(ring)
+                .collect()
+        })
+        .collect()
+}
+
+pub fn from_geo_polygons(grid: &GridSystem, polygons: &GeoPolygons) -> MultiPolygon<f64> {
+    let ring = |ring: &Vec<GeoPoint>| -> LineString<f64> {
+        ring.iter()
+            .map(|point| {
+                let projected = grid.project_point(point);
+                Coord {
+                    x: projected.easting,
+                    y: projected.northing,
+                }
+            })
+            .collect()
+    };
+    MultiPolygon::new(
+        polygons
+            .iter()
+            .filter(|rings| !rings.is_empty())
+            .map(|rings| Polygon::new(ring(&rings[0]), rings[1..].iter().map(ring).collect()))
+            .collect(),
+    )
+}
+
+/// Union of all swaths driven so far, built up one track point at a time.
+#[derive(Clone, Debug)]
+pub struct Coverage {
+    pub grid: GridSystem,
+    polygons: MultiPolygon<f64>,
+    last: Option<SwathPoint>,
+    last_direction: Option<f64>,
+}
+
+impl Coverage {
+    pub fn new(grid: GridSystem) -> Self {
+        Coverage {
+            grid,
+            polygons: MultiPolygon::new(vec![]),
+            last: None,
+            last_direction: None,
+        }
+    }
+
+    /// Extends the coverage by the swath from the previous point to `next`.
+    pub fn add(&mut self, next: SwathPoint) {
+        let Some(last) = self.last else {
+            self.last = Some(next);
+            return;
+        };
+        if last.point.distance(&next.point) < MIN_SEGMENT_LENGTH {
+            return;
+        }
+        let direction = bearing(last.point, next.point);
+        if let Some(wedge) = self
+            .last_direction
+            .and_then(|previous| turn_wedge(last, previous, direction))
+        {
+            self.union(wedge);
+        }
+        if let Some(swath) = segment_swath(last, next) {
+            self.union(swath);
+        }
+        self.last = Some(next);
+        self.last_direction = Some(direction);
+    }
+
+    /// Ends the current swath, the next point starts a new one.
+    pub fn interrupt(&mut self) {
+        self.last = None;
+        self.last_direction = None;
+    }
+
+    fn union(&mut self, polygon: Polygon<f64>) {
+        self.polygons = self.polygons.union(&MultiPolygon::new(vec![polygon]));
+    }
+
+    pub fn polygons(&self) -> &MultiPolygon<f64> {
+        &self.polygons
+    }
+
+    pub fn into_polygons(self) -> MultiPolygon<f64> {
+        self.polygons
+    }
+
+    /// Covered area on the ground in m².
+    pub fn area(&self) -> f64 {
+        ground_area(&self.grid, &self.polygons)
+    }
+}
+
+/// Running unions of the passes merged so far.
+#[derive(Clone, Debug)]
+struct MergedPasses {
+    coverage: MultiPolygon<f64>,
+    overlaps: MultiPolygon<f64>,
+    wide_coverage: MultiPolygon<f64>,
+    between_passes: MultiPolygon<f64>,
+    reach_coverage: MultiPolygon<f64>,
+    within_reach: MultiPolygon<f64>,
+}
+
+impl MergedPasses {
+    fn new() -> Self {
+        MergedPasses {
+            coverage: MultiPolygon::new(vec![]),
+            overlaps: MultiPolygon::new(vec![]),
+            wide_coverage: MultiPolygon::new(vec![]),
+            between_passes: MultiPolygon::new(vec![]),
+            reach_coverage: MultiPolygon::new(vec![]),
+            within_reach: MultiPolygon::new(vec![]),
+        }
+    }
+
+    /// Adds a pass. `wide_pass` is the same pass built wider by the gap threshold, so
+    /// two wide passes meet where the passes are closer than it. `reach_pass` is wider
+    /// by the threshold on both sides and spans the whole of such a gap.
+    fn merge(
+        &mut self,
+        pass: &MultiPolygon<f64>,
+        wide_pass: &MultiPolygon<f64>,
+        reach_pass: &MultiPolygon<f64>,
+    ) {
+        if pass.0.is_empty() {
+            return;
+        }
+        self.overlaps = self.overlaps.union(&pass.intersection(&self.coverage));
+        self.coverage = self.coverage.union(pass);
+        self.between_passes = self
+            .between_passes
+            .union(&wide_pass.intersection(&self.wide_coverage));
+        self.wide_coverage = self.wide_coverage.union(wide_pass);
+        self.within_reach = self
+            .within_reach
+            .union(&reach_pass.intersection(&self.reach_coverage));
+        self.reach_coverage = self.reach_coverage.union(reach_pass);
+    }
+
+    /// Uncovered parts of `area` larger than numerical noise.
+    fn uncovered(&self, area: &MultiPolygon<f64>) -> Vec<Polygon<f64>> {
+        area.difference(&self.coverage)
+            .into_iter()
+            .filter(|gap| gap.unsigned_area() > MIN_GAP_AREA)
+            .collect()
+    }
+
+    fn into_analysis(self, grid: GridSystem) -> PassAnalysis {
+        // the wide passes only meet across part of a gap, the gap is the whole
+        // uncovered area around that within reach of both passes
+        let narrow = self.uncovered(&self.between_passes);
+        let gaps = MultiPolygon::new(
+            self.uncovered(&self.within_reach)
+                .into_iter()
+                .filter(|gap| narrow.iter().any(|part| gap.intersects(part)))
+                .collect(),
+        );
+        PassAnalysis {
+            grid,
+            coverage: self.coverage,
+            overlaps: self.overlaps,
+            gaps,
+        }
+    }
+}
+
+/// Builds the [`PassAnalysis`] of a track one point at a time.
+///
+/// Each pass is merged into the result once when it ends, instead of rebuilding every
+/// pass of the session on every update.
+#[derive(Clone, Debug)]
+pub struct PassAnalyser {
+    pub grid: GridSystem,
+    gap_threshold: f64,
+    merged: MergedPasses,
+    pass: Coverage,
+    wide_pass: Coverage,
+    reach_pass: Coverage,
+    last: Option<SwathPoint>,
+    start_direction: Option<f64>,
+}
+
+impl PassAnalyser {
+    /// Gaps between two passes narrower than `gap_threshold` metres are reported, wider
+    /// ones are assumed to be intentional.
+    pub fn new(grid: GridSystem, gap_threshold: f64) -> Self {
+        PassAnalyser {
+            pass: Coverage::new(grid.clone()),
+            wide_pass: Coverage::new(grid.clone()),
+            reach_pass: Coverage::new(grid.clone()),
+            grid,
+            gap_threshold,
+            merged: MergedPasses::new(),
+            last: None,
+            start_direction: None,
+        }
+    }
+
+    pub fn gap_threshold(&self) -> f64 {
+        self.gap_threshold
+    }
+
+    /// Adds the next track point. Passes end where the deck was off or recording paused
+    /// and at every turn and reversal.
+    pub fn add(&mut self, point: &TrackPoint) {
+        if !point.engaged || point.after_gap {
+            self.finish_pass();
+        }
+        if !point.engaged {
+            return;
+        }
+        let next = point.swath_point(&self.grid);
+        if let Some(last) = self.last {
+            if last.point.distance(&next.point) < MIN_SEGMENT_LENGTH {
+                return;
+            }
+            let direction = bearing(last.point, next.point);
+            match self.start_direction {
+                Some(start) if turn_angle(start, direction).abs() > PASS_TURN_ANGLE.to_radians() => {
+                    // the new pass starts where the last one ended
+                    self.finish_pass();
+                    self.extend_pass(last);
+                    self.start_direction = Some(direction);
+                }
+                None => self.start_direction = Some(direction),
+                _ => {}
+            }
+        }
+        self.extend_pass(next);
+    }
+
+    fn extend_pass(&mut self, point: SwathPoint) {
+        self.pass.add(point);
+        self.wide_pass
+            .add(SwathPoint::new(point.point, point.width + self.gap_threshold));
+        self.reach_pass
+            .add(SwathPoint::new(point.point, point.width + 2.0 * self.gap_threshold));
+        self.last = Some(point);
+    }
+
+    fn finish_pass(&mut self) {
+        let pass = std::mem::replace(&mut self.pass, Coverage::new(self.grid.clone()));
+        let wide_pass = std::mem::replace(&mut self.wide_pass, Coverage::new(self.grid.clone()));
+        let reach_pass = std::mem::replace(&mut self.reach_pass, Coverage::new(self.grid.clone()));
+        self.merged
+            .merge(pass.polygons(), wide_pass.polygons(), reach_pass.polygons());
+        self.last = None;
+        self.start_direction = None;
+    }
+
+    /// The analysis of all points so far, including the pass still being driven.
+    pub fn analysis(&self) -> PassAnalysis {
+        let mut merged = self.merged.clone();
+        merged.merge(
+            self.pass.polygons(),
+            self.wide_pass.polygons(),
+            self.reach_pass.polygons(),
+        );
+        merged.into_analysis(self.grid.clone())
+    }
+}
+
+/// Gaps and overlaps between the passes of a track, in grid coordinates.
+#[derive(Clone, Debug)]
+pub struct PassAnalysis {
+    pub grid: GridSystem,
+    pub coverage: MultiPolygon<f64>,
+    pub overlaps: MultiPolygon<f64>,
+    pub gaps: MultiPolygon<f64>,
+}
+
+impl PassAnalysis {
+    /// Builds the passes of the engaged parts of `track` and analyses them.
+    ///
+    /// Gaps between two passes narrower than `gap_threshold` metres are reported, wider
+    /// ones are assumed to be intentional.
+    pub fn from_track(grid: GridSystem, track: &[TrackPoint], gap_threshold: f64) -> Self {
+        let mut analyser = PassAnalyser::new(grid, gap_threshold);
+        for point in track {
+            analyser.add(point);
+        }
+        analyser.analysis()
+    }
+
+    pub fn covered_area(&self) -> f64 {
+        ground_area(&self.grid, &self.coverage)
+    }
+
+    pub fn overlap_area(&self) -> f64 {
+        ground_area(&self.grid, &self.overlaps)
+    }
+
+    pub fn gap_area(&self) -> f64 {
+        ground_area(&self.grid, &self.gaps)
+    }
+
+    /// Share of the covered area that was mowed more than once.
+    pub fn overlap_percentage(&self) -> f64 {
+        let covered = self.covered_area();
+        if covered > 0.0 {
+            self.overlap_area() / covered * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{assert_close, grid, swath, track_point};
+
+    /// The test tracks run along the central meridian, where the scale factor is 0.9996.
+    fn assert_area(actual: f64, grid_area: f64) {
+        assert_close(actual, grid_area / (0.9996 * 0.9996), 1e-3);
+    }
+
+    /// Two passes northwards `spacing` metres apart, with transit between them.
+    fn two_passes(grid: &GridSystem, spacing: f64) -> Vec<TrackPoint> {
+        vec![
+            track_point(grid, 0.0, 0.0, true),
+            track_point(grid, 0.0, 10.0, true),
+            track_point(grid, spacing, 0.0, false),
+            track_point(grid, spacing, 0.0, true),
+            track_point(grid, spacing, 10.0, true),
+        ]
+    }
+
+    #[test]
+    fn straight_swath() {
+        let mut coverage = Coverage::new(grid());
+        coverage.add(swath(0.0, 0.0, 1.0));
+        coverage.add(swath(0.0, 4.0, 1.0));
+        coverage.add(swath(0.0, 10.0, 1.0));
+        assert_area(coverage.area(), 10.0);
+        // driving the same way back covers nothing new
+        coverage.add(swath(0.0, 0.0, 1.0));
+        assert_area(coverage.area(), 10.0);
+    }
+
+    #[test]
+    fn changing_width() {
+        let mut coverage = Coverage::new(grid());
+        coverage.add(swath(0.0, 0.0, 1.0));
+        coverage.add(swath(0.0, 10.0, 2.0));
+        assert_area(coverage.area(), 15.0);
+    }
+
+    #[test]
+    fn turn_fills_the_outside_corner() {
+        let mut coverage = Coverage::new(grid());
+        coverage.add(swath(0.0, 0.0, 1.0));
+        coverage.add(swath(0.0, 10.0, 1.0));
+        coverage.add(swath(10.0, 10.0, 1.0));
+        // two swaths sharing a 0.5 m square plus a fan of six 15° triangles
+        let wedge = 6.0 * 0.5 * 0.25 * WEDGE_STEP.to_radians().sin();
+        assert_area(coverage.area(), 20.0 - 0.25 + wedge);
+    }
+
+    #[test]
+    fn interrupted_swath() {
+        let mut coverage = Coverage::new(grid());
+        coverage.add(swath(0.0, 0.0, 1.0));
+        coverage.add(swath(0.0, 10.0, 1.0));
+        coverage.interrupt();
+        coverage.add(swath(5.0, 10.0, 1.0));
+        coverage.add(swath(5.0, 0.0, 1.0));
+        assert_eq!(coverage.polygons().0.len(), 2);
+        assert_area(coverage.area(), 20.0);
+    }
+
+    #[test]
+    fn no_swath_without_length_or_width() {
+        assert_eq!(segment_swath(swath(0.0, 0.0, 1.0), swath(0.0, 0.0, 1.0)), None);
+        assert_eq!(segment_swath(swath(0.0, 0.0, 0.0), swath(0.0, 5.0, 0.0)), None);
+    }
+
+    #[test]
+    fn no_wedge_when_reversing() {
+        let center = swath(0.0, 0.0, 1.0);
+        assert_eq!(turn_wedge(center, 0.0, 0.0), None);
+        assert_eq!(turn_wedge(center, 0.0, std::f64::consts::PI), None);
+        assert!(turn_wedge(center, 0.0, std::f64::consts::FRAC_PI_2).is_some());
+    }
+
+    #[test]
+    fn geo_polygons_round_trip() {
+        let grid = grid();
+        let mut coverage = Coverage::new(grid.clone());
+        coverage.add(swath(0.0, 0.0, 1.0));
+        coverage.add(swath(0.0, 10.0, 1.0));
+        let back = from_geo_polygons(&grid, &to_geo_polygons(&grid, coverage.polygons()));
+        assert!((back.unsigned_area() - coverage.polygons().unsigned_area()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn overlapping_passes() {
+        let grid = grid();
+        let analysis = PassAnalysis::from_track(grid.clone(), &two_passes(&grid, 0.9), 0.5);
+        assert_area(analysis.covered_area(), 19.0);
+        assert_area(analysis.overlap_area(), 1.0);
+        assert_eq!(analysis.gap_area(), 0.0);
+        assert!((analysis.overlap_percentage() - 100.0 / 19.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn gap_between_passes() {
+        let grid = grid();
+        let analysis = PassAnalysis::from_track(grid.clone(), &two_passes(&grid, 1.3), 0.5);
+        assert_area(analysis.gap_area(), 3.0);
+        assert_eq!(analysis.overlap_area(), 0.0);
+    }
+
+    #[test]
+    fn wide_gaps_are_intended() {
+        let grid = grid();
+        // 0.7 m apart, within reach of both passes but wider than the threshold
+        let analysis = PassAnalysis::from_track(grid.clone(), &two_passes(&grid, 1.7), 0.5);
+        assert_eq!(analysis.gap_area(), 0.0);
+        assert_area(analysis.covered_area(), 20.0);
+    }
+
+    #[test]
+    fn turn_ends_a_pass() {
+        let grid = grid();
+        // up, across and down again without lifting the deck
+        let track = [
+            track_point(&grid, 0.0, 0.0, true),
+            track_point(&grid, 0.0, 10.0, true),
+            track_point(&grid, 0.9, 10.0, true),
+            track_point(&grid, 0.9, 0.0, true),
+        ];
+        let analysis = PassAnalysis::from_track(grid, &track, 0.5);
+        // the strip between the two long passes, not the corners they share with the turn
+        assert!(analysis.overlap_area() > 0.9);
+    }
+
+    #[test]
+    fn analyser_matches_whole_track() {
+        let grid = grid();
+        let track = two_passes(&grid, 1.3);
+        let mut analyser = PassAnalyser::new(grid.clone(), 0.5);
+        let mut analysis = analyser.analysis();
+        for point in &track {
+            analyser.add(point);
+            analysis = analyser.analysis();
+        }
+        let whole = PassAnalysis::from_track(grid, &track, 0.5);
+        assert_eq!(analysis.coverage, whole.coverage);
+        assert_eq!(analysis.gaps, whole.gaps);
+    }
+
+    #[test]
+    fn vehicle_heading_while_reversing() {
+        use std::f64::consts::{FRAC_PI_2, PI};
+        let mut heading = VehicleHeading::default();
+        assert_eq!(heading.heading(), None);
+        heading.update(0.0, 0);
+        heading.update(0.1, 1000);
+        assert!((heading.heading().unwrap() - 0.1).abs() < 1e-12);
+        // backing up: the direction of travel flips, the vehicle still faces north
+        heading.update(PI + 0.1, 2000);
+        assert!((heading.heading().unwrap() - 0.1).abs() < 1e-12);
+        // reversing round a corner turns the vehicle gradually
+        heading.update(PI + 0.3, 3000);
+        assert!((heading.heading().unwrap() - 0.3).abs() < 1e-12);
+        // driving forwards again
+        heading.update(0.3, 4000);
+        heading.update(FRAC_PI_2, 5000);
+        assert!((heading.heading().unwrap() - FRAC_PI_2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn vehicle_heading_after_turning_on_the_spot() {
+        use std::f64::consts::PI;
+        let mut heading = VehicleHeading::default();
+        heading.update(0.0, 0);
+        heading.update(0.0, 1000);
+        // stop at the headland, pivot and drive back: forwards in the new direction
+        heading.update(PI, 10_000);
+        assert!((heading.heading().unwrap().abs() - PI).abs() < 1e-12);
+        heading.update(PI - 0.1, 11_000);
+        assert!((heading.heading().unwrap() - (PI - 0.1)).abs() < 1e-12);
+        // backing up after the stop is still recognised while moving
+        heading.update(-0.1, 12_000);
+        assert!((heading.heading().unwrap() - (PI - 0.1)).abs() < 1e-12);
+        // stopping while reversing and driving off forwards
+        heading.update(-0.1, 20_000);
+        assert!((heading.heading().unwrap() + 0.1).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,344 @@
+use serde::{Deserialize, Serialize};
+
+use crate::projection::{GeoPoint, GridSystem, Projection, ProjectedPoint};
+
+/// Narrowest pass spacing accepted, so a zero mower width cannot divide by zero.
+const MIN_SPACING: f64 = 0.1;
+/// Distance along the line used to derive the look-ahead direction in metres.
+const LOOK_AHEAD: f64 = 3.0;
+/// Minimum distance between two recorded points of a curved reference pass in metres.
+pub const CURVE_POINT_DISTANCE: f64 = 1.0;
+/// How far contours are drawn beyond the ends of the reference in metres.
+const CONTOUR_EXTENSION: f64 = 200.0;
+
+/// Straight reference line through the points A and B.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AbLine {
+    pub a: GeoPoint,
+    pub b: GeoPoint,
+}
+
+impl AbLine {
+    pub fn new(a: GeoPoint, b: GeoPoint) -> Self {
+        AbLine { a, b }
+    }
+}
+
+/// Recorded reference pass that the guidance contours follow.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CurveLine {
+    pub points: Vec<GeoPoint>,
+}
+
+impl CurveLine {
+    /// Adds `point` if it is far enough from the last recorded one.
+    pub fn record(&mut self, grid: &GridSystem, point: GeoPoint) {
+        let far_enough = self.points.last().map_or(true, |last| {
+            grid.project_point(last).distance(&grid.project_point(&point)) >= CURVE_POINT_DISTANCE
+        });
+        if far_enough {
+            self.points.push(point);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GuidanceLine {
+    Ab(AbLine),
+    Curve(CurveLine),
+}
+
+/// Where the mower is relative to the nearest guidance line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineGuidance {
+    /// index of the nearest parallel line, 0 is the reference itself, positive to the right
+    pub pass: i32,
+    /// signed distance to that line in metres, positive when the mower is right of it
+    /// as seen in the direction of travel
+    pub cross_track_error: f64,
+    /// angle between the heading and the look-ahead point in degrees, positive means steer right
+    pub heading_error: Option<f64>,
+}
+
+impl GuidanceLine {
+    fn first_point(&self) -> Option<&GeoPoint> {
+        match self {
+            GuidanceLine::Ab(line) => Some(&line.a),
+            GuidanceLine::Curve(curve) => curve.points.first(),
+        }
+    }
+
+    /// The grid the line is evaluated in, fixed by its first point.
+    pub fn grid(&self, projection: Projection) -> Option<GridSystem> {
+        self.first_point().map(|point| projection.grid_at(point))
+    }
+
+    fn reference(&self, grid: &GridSystem) -> Option<ReferencePath> {
+        let points = match self {
+            GuidanceLine::Ab(line) => vec![grid.project_point(&line.a), grid.project_point(&line.b)],
+            GuidanceLine::Curve(curve) => curve.points.iter().map(|p| grid.project_point(p)).collect(),
+        };
+        ReferencePath::new(points)
+    }
+
+    /// Guidance for `position` with parallel lines `spacing` metres apart.
+    ///
+    /// `heading` is the direction of travel in radians clockwise from north; without it no
+    /// look-ahead direction is given. Returns `None` if the line is too short to define a
+    /// direction.
+    pub fn guidance(
+        &self,
+        grid: &GridSystem,
+        position: ProjectedPoint,
+        heading: Option<f64>,
+        spacing: f64,
+    ) -> Option<LineGuidance> {
+        let reference = self.reference(grid)?;
+        let (offset, along) = reference.locate(position);
+        let spacing = spacing.max(MIN_SPACING);
+        let pass = (offset / spacing).round();
+
+        let (tangent_e, tangent_n) = reference.tangent_at(along);
+        let forward = heading.map_or(true, |heading| {
+            tangent_e * heading.sin() + tangent_n * heading.cos() >= 0.0
+        });
+        let cross_track_error = offset - pass * spacing;
+
+        let heading_error = heading.map(|heading| {
+            let target = reference.point_at(
+                if forward { along + LOOK_AHEAD } else { along - LOOK_AHEAD },
+                pass * spacing,
+            );
+            let bearing = (target.easting - position.easting).atan2(target.northing - position.northing);
+            wrap_angle(bearing - heading).to_degrees()
+        });
+
+        Some(LineGuidance {
+            pass: pass as i32,
+            cross_track_error: if forward { cross_track_error } else { -cross_track_error },
+            heading_error,
+        })
+    }
+
+    /// Contours of the passes `first..=last`, in WGS84 for drawing.
+    ///
+    /// They continue [`CONTOUR_EXTENSION`] past both ends, as the guidance does.
+    pub fn contours(
+        &self,
+        grid: &GridSystem,
+        spacing: f64,
+        passes: std::ops::RangeInclusive<i32>,
+    ) -> Vec<Vec<GeoPoint>> {
+        let Some(reference) = self.reference(grid) else {
+            return Vec::new();
+        };
+        let spacing = spacing.max(MIN_SPACING);
+        passes
+            .map(|pass| {
+                reference
+                    .offset_contour(pass as f64 * spacing)
+                    .into_iter()
+                    .map(|point| {
+                        let (latitude, longitude) = grid.unproject(point);
+                        GeoPoint { latitude, longitude }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Spacing between two passes, the mower width minus the wanted overlap.
+pub fn pass_spacing(mower_width: f64, overlap: f64) -> f64 {
+    (mower_width - overlap).max(MIN_SPACING)
+}
+
+fn wrap_angle(angle: f64) -> f64 {
+    let full = std::f64::consts::TAU;
+    (angle + full / 2.0).rem_euclid(full) - full / 2.0
+}
+
+/// Polyline in grid coordinates whose first and last segments extend to infinity.
+struct ReferencePath {
+    points: Vec<ProjectedPoint>,
+    /// distance along the path at each point
+    stations: Vec<f64>,
+}
+
+impl ReferencePath {
+    fn new(points: Vec<ProjectedPoint>) -> Option<Self> {
+        let mut deduplicated: Vec<ProjectedPoint> = Vec::with_capacity(points.len());
+        for point in points {
+            if deduplicated.last().map_or(true, |last| last.distance(&point) >= MIN_SPACING) {
+                deduplicated.push(point);
+            }
+        }
+        if deduplicated.len() < 2 {
+            return None;
+        }
+        let mut stations = vec![0.0];
+        for pair in deduplicated.windows(2) {
+            stations.push(stations.last().unwrap() + pair[0].distance(&pair[1]));
+        }
+        Some(ReferencePath {
+            points: deduplicated,
+            stations,
+        })
+    }
+
+    fn segment_count(&self) -> usize {
+        self.points.len() - 1
+    }
+
+    fn direction(&self, segment: usize) -> (f64, f64) {
+        let (a, b) = (self.points[segment], self.points[segment + 1]);
+        let length = self.stations[segment + 1] - self.stations[segment];
+        ((b.easting - a.easting) / length, (b.northing - a.northing) / length)
+    }
+
+    /// Signed offset (positive right) and distance along the path of the closest point.
+    fn locate(&self, position: ProjectedPoint) -> (f64, f64) {
+        let last = self.segment_count() - 1;
+        let mut best = (f64::INFINITY, 0.0, 0.0);
+        for segment in 0..=last {
+            let a = self.points[segment];
+            let length = self.stations[segment + 1] - self.stations[segment];
+            let (dir_e, dir_n) = self.direction(segment);
+            let (rel_e, rel_n) = (position.easting - a.easting, position.northing - a.northing);
+            let mut t = rel_e * dir_e + rel_n * dir_n;
+            if segment > 0 {
+                t = t.max(0.0);
+            }
+            if segment < last {
+                t = t.min(length);
+            }
+            let (foot_e, foot_n) = (a.easting + t * dir_e, a.northing + t * dir_n);
+            let distance = (position.easting - foot_e).hypot(position.northing - foot_n);
+            if distance < best.0 {
+                let side = rel_e * dir_n - rel_n * dir_e;
+                best = (distance, distance.copysign(side), self.stations[segment] + t);
+            }
+        }
+        (best.1, best.2)
+    }
+
+    fn segment_at(&self, along: f64) -> usize {
+        let index = self.stations.partition_point(|station| *station <= along);
+        index.saturating_sub(1).min(self.segment_count() - 1)
+    }
+
+    fn tangent_at(&self, along: f64) -> (f64, f64) {
+        self.direction(self.segment_at(along))
+    }
+
+    fn point_at(&self, along: f64, offset: f64) -> ProjectedPoint {
+        let segment = self.segment_at(along);
+        let (dir_e, dir_n) = self.direction(segment);
+        let t = along - self.stations[segment];
+        let a = self.points[segment];
+        ProjectedPoint::new(
+            a.easting + t * dir_e + offset * dir_n,
+            a.northing + t * dir_n - offset * dir_e,
+        )
+    }
+
+    /// The path shifted sideways by `offset` metres (positive right), with mitred corners
+    /// and extended by [`CONTOUR_EXTENSION`] at both ends.
+    fn offset_contour(&self, offset: f64) -> Vec<ProjectedPoint> {
+        let last = self.segment_count() - 1;
+        let end = self.stations[last + 1];
+        let start = self.point_at(-CONTOUR_EXTENSION, offset);
+        let finish = self.point_at(end + CONTOUR_EXTENSION, offset);
+        let corners = (0..self.points.len()).map(|i| {
+            let before = self.direction(i.saturating_sub(1).min(last));
+            let after = self.direction(i.min(last));
+            let (normal_e, normal_n) = (before.1 + after.1, -(before.0 + after.0));
+            let length = normal_e.hypot(normal_n);
+            // the cosine of half the turn angle, limited so sharp turns stay bounded
+            let miter = (length / 2.0).max(0.5);
+            let p = self.points[i];
+            if length < f64::EPSILON {
+                return p;
+            }
+            ProjectedPoint::new(
+                p.easting + offset * normal_e / length / miter,
+                p.northing + offset * normal_n / length / miter,
+            )
+        });
+        std::iter::once(start).chain(corners).chain(std::iter::once(finish)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geo_point(grid: &GridSystem, easting: f64, northing: f64) -> GeoPoint {
+        let (latitude, longitude) = grid.unproject(ProjectedPoint::new(easting, northing));
+        GeoPoint { latitude, longitude }
+    }
+
+    /// A line running north from A, 100 m long.
+    fn north_line() -> (GridSystem, GuidanceLine) {
+        let grid = Projection::Utm.grid(52.0, 9.0);
+        let a = geo_point(&grid, 500_000.0, 5_761_000.0);
+        let b = geo_point(&grid, 500_000.0, 5_761_100.0);
+        (grid, GuidanceLine::Ab(AbLine::new(a, b)))
+    }
+
+    #[test]
+    fn spacing() {
+        assert!((pass_spacing(2.0, 0.1) - 1.9).abs() < 1e-12);
+        assert_eq!(pass_spacing(0.0, 0.1), MIN_SPACING);
+        assert_eq!(pass_spacing(1.0, 2.0), MIN_SPACING);
+    }
+
+    #[test]
+    fn cross_track_error() {
+        let (grid, line) = north_line();
+        let position = ProjectedPoint::new(500_002.3, 5_761_050.0);
+        let guidance = line.guidance(&grid, position, Some(0.0), 2.0).unwrap();
+        assert_eq!(guidance.pass, 1);
+        assert!((guidance.cross_track_error - 0.3).abs() < 1e-6);
+        // right of the line, so the mower has to steer left onto it
+        let expected = (-0.3f64).atan2(LOOK_AHEAD).to_degrees();
+        assert!((guidance.heading_error.unwrap() - expected).abs() < 1e-4);
+
+        let left = ProjectedPoint::new(499_996.2, 5_761_050.0);
+        let guidance = line.guidance(&grid, left, None, 2.0).unwrap();
+        assert_eq!(guidance.pass, -2);
+        assert!((guidance.cross_track_error - 0.2).abs() < 1e-6);
+        assert_eq!(guidance.heading_error, None);
+    }
+
+    #[test]
+    fn driving_back() {
+        let (grid, line) = north_line();
+        let position = ProjectedPoint::new(500_002.3, 5_761_050.0);
+        let guidance = line
+            .guidance(&grid, position, Some(std::f64::consts::PI), 2.0)
+            .unwrap();
+        // the pass keeps its number, but right and left swap with the direction of travel
+        assert_eq!(guidance.pass, 1);
+        assert!((guidance.cross_track_error + 0.3).abs() < 1e-6);
+        assert!(guidance.heading_error.unwrap() > 0.0);
+    }
+
+    #[test]
+    fn beyond_the_ends() {
+        let (grid, line) = north_line();
+        let position = ProjectedPoint::new(500_001.0, 5_761_300.0);
+        let guidance = line.guidance(&grid, position, Some(0.0), 2.0).unwrap();
+        assert_eq!(guidance.pass, 1);
+        assert!((guidance.cross_track_error + 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn line_too_short() {
+        let grid = Projection::Utm.grid(52.0, 9.0);
+        let a = geo_point(&grid, 500_000.0, 5_761_000.0);
+        let line = GuidanceLine::Ab(AbLine::new(a, a));
+        let position = ProjectedPoint::new(500_001.0, 5_761_000.0);
+        assert_eq!(line.guidance(&grid, position, None, 2.0), None);
+    }
+}
@@ -0,0 +1,65 @@
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+
+use crate::components::SelectOption;
+use crate::utils::get_lang_code;
+
+/// Language of the interface, each one needs a file in `locales/`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    /// whatever the browser prefers
+    #[default]
+    Browser,
+    English,
+    German,
+    French,
+    Dutch,
+    Spanish,
+}
+
+impl Language {
+    /// Locale code as used for the file names in `locales/`.
+    pub fn code(&self) -> String {
+        match self {
+            Language::Browser => {
+                let lang = get_lang_code();
+                lang.split('-').next().unwrap_or("en").to_string()
+            }
+            Language::English => String::from("en"),
+            Language::German => String::from("de"),
+            Language::French => String::from("fr"),
+            Language::Dutch => String::from("nl"),
+            Language::Spanish => String::from("es"),
+        }
+    }
+
+    /// Texts created afterwards are in this language, unknown locales fall back to English.
+    pub fn apply(&self) {
+        rust_i18n::set_locale(&self.code());
+    }
+}
+
+impl SelectOption for Language {
+    fn all() -> Vec<Self> {
+        vec![
+            Language::Browser,
+            Language::English,
+            Language::German,
+            Language::French,
+            Language::Dutch,
+            Language::Spanish,
+        ]
+    }
+
+    /// Languages are named in themselves, so they can be found without understanding the current one.
+    fn lable(&self) -> String {
+        match self {
+            Language::Browser => t!("language_browser"),
+            Language::English => String::from("English"),
+            Language::German => String::from("Deutsch"),
+            Language::French => String::from("Français"),
+            Language::Dutch => String::from("Nederlands"),
+            Language::Spanish => String::from("Español"),
+        }
+    }
+}
@@ -1,27 +1,62 @@
+mod background;
 mod components;
+mod database;
+mod fields;
+mod geometry;
+mod guidance;
+mod language;
 mod mutex_box;
 mod position;
+mod profile;
+mod projection;
+mod report;
+mod sensor_log;
+mod session;
+mod settings;
+#[cfg(test)]
+mod test_utils;
+mod tiles;
+mod units;
 mod utils;
 
+use crate::background::{keep_screen_on, watch_visibility};
+use crate::components::deck::DeckButton;
+use crate::components::fields::FieldManager;
+use crate::components::guidance::Guidance;
+use crate::components::hud::Hud;
+use crate::components::map::MowMap;
+use crate::components::offline::OfflineTiles;
+use crate::components::permissions::PermissionScreen;
+use crate::components::profiles::ProfileEditor;
 use crate::components::raw_data::RawValues;
-use crate::components::{MenuButtons, ValueInput};
-use crate::position::{get_global_position, start_web_data};
-use crate::utils::create_stored_signal;
+use crate::components::report::SessionReport;
+use crate::components::settings::SettingsTransfer;
+use crate::components::{CheckboxInput, MenuButtons, SelectInput, ValueInput};
+use crate::database::{load_all, FIELDS, JOBS, SITES};
+use crate::fields::{field_history, Field, Job, LastMowed, Site};
+use crate::geometry::{Coverage, PassAnalyser, PassAnalysis, VehicleHeading};
+use crate::language::Language;
+use crate::position::{set_geolocation_options, GeolocationOptions, SensorStore};
+use crate::profile::MowerProfile;
+use crate::projection::Projection;
+use crate::session::{Session, TrackPoint};
+use crate::settings::{create_settings, SettingsSignals};
+use crate::tiles::{GeoBounds, TileSource};
+use crate::units::Unit;
+use chrono::Utc;
 use git_version::git_version;
 use gloo_timers::future::TimeoutFuture;
-use nav_types::ECEF;
+use nav_types::WGS84;
 use rust_i18n::t;
 use sycamore::futures::spawn_local_scoped;
 use sycamore::prelude::*;
-use utils::get_lang_code;
 
 const GIT_VERSION: &str = git_version!(args = ["--always", "--tags"]);
 rust_i18n::i18n!("locales", fallback = "en");
 
 fn main() {
-    let lang = get_lang_code();
-    let lang: Vec<&str> = lang.as_str().split("-").collect();
-    rust_i18n::set_locale(lang[0]);
+    // until the settings are loaded
+    Language::Browser.apply();
 
     sycamore::render(|| {
         view! {
@@ -32,14 +67,106 @@ fn main() {
 
 #[component]
 fn App<G: Html>() -> View<G> {
-    start_web_data();
+    let sensors = SensorStore::new();
+    provide_context(sensors.clone());
 
-    let mower_width = create_stored_signal(String::from("mower_with"), 0.0f64);
-
-    //let accuracy = create_stored_signal(String::from("accuracy"), 0.0f64);
+    let settings = create_settings();
+    let SettingsSignals {
+        language,
+        profiles,
+        operator,
+        active_profile,
+        projection,
+        units,
+        guidance_overlap,
+        gap_threshold,
+        deck_auto_detect,
+        vibration_threshold,
+        guidance_line,
+        geo_high_accuracy,
+        geo_maximum_age,
+        geo_timeout,
+        active_field,
+        show_last_mowed,
+        offline_max_zoom,
+        tile_source,
+        custom_tile_url,
+    } = settings;
+    provide_context(*units);
+    // texts are translated when they are created, another language needs a reload
+    let started_in = language.get_untracked();
+    started_in.apply();
+    let profile = create_memo(move || {
+        profiles.with(|profiles| {
+            profiles
+                .get(active_profile.get())
+                .cloned()
+                .unwrap_or_else(|| MowerProfile::new(String::new()))
+        })
+    });
+    let mower_width = create_memo(move || profile.with(|profile| profile.width));
+    {
+        let sensors = sensors.clone();
+        create_effect(move || sensors.set_fusion_preset(profile.with(|profile| profile.eskf_preset)));
+    }
+    create_effect(move || {
+        // 0 s would fail every fix at once, it stands for no timeout
+        let timeout = geo_timeout.get();
+        set_geolocation_options(GeolocationOptions {
+            high_accuracy: geo_high_accuracy.get(),
+            maximum_age: (geo_maximum_age.get().max(0.0) * 1000.0) as u32,
+            timeout: (timeout > 0.0).then(|| (timeout * 1000.0) as u32),
+        })
+    });
 
+    let permissions_open = create_signal(true);
     let menu_visible = create_signal(false);
     let raw_visable = create_signal(false);
+    let session = create_signal(Session::new());
+    let deck_engaged = create_signal(false);
+    let coverage = create_signal(None::<Coverage>);
+    let analysis = create_signal(None::<PassAnalysis>);
+    let view_bounds = create_signal(None::<GeoBounds>);
+    let tile_server = create_memo(move || custom_tile_url.with(|url| tile_source.get().server(url)));
+    let guide_lines = create_signal(Vec::new());
+
+    let recording = create_memo(move || session.with(Session::is_recording));
+    create_effect(move || keep_screen_on(recording.get()));
+    let paused_sensors = sensors.clone();
+    watch_visibility(move |gap| {
+        if let Some((start, end)) = gap {
+            // the sensors were paused, integrating over the gap would only add drift
+            paused_sensors.reset();
+            session.update(|s| s.mark_gap(start, end));
+            coverage.update(|coverage| {
+                if let Some(coverage) = coverage {
+                    coverage.interrupt();
+                }
+            });
+        }
+        keep_screen_on(recording.get_untracked());
+    });
+
+    let sites = create_signal(Vec::<Site>::new());
+    let fields = create_signal(Vec::<Field>::new());
+    let jobs = create_signal(Vec::<Job>::new());
+    let field = create_memo(move || {
+        active_field.with(|active| {
+            fields.with(|fields| fields.iter().find(|field| Some(field.id) == *active).cloned())
+        })
+    });
+    let last_mowed = create_memo(move || {
+        if !show_last_mowed.get() {
+            return None;
+        }
+        let field = active_field.get()?;
+        jobs.with(|jobs| LastMowed::new(projection.get(), &field_history(jobs, field)))
+    });
+    spawn_local_scoped(async move {
+        sites.set(load_all(SITES).await);
+        fields.set(load_all(FIELDS).await);
+        jobs.set(load_all(JOBS).await);
+    });
 
     let result = view! {
         header{
@@ -52,31 +179,135 @@ fn App<G: Html>() -> View<G> {
                 MenuButtons(raw_visable=raw_visable,menu_visable=menu_visible)
                 br{}
                 div(class="triple-column"){
-            ValueInput(lable=t!("mower_width"),value=mower_width){"m"}
-            RawValues(raw_visable=raw_visable,menu_visable=menu_visible)
-        }}
+            ProfileEditor(profiles=profiles,active=active_profile)
+            span{(t!("operator"))}
+            input(bind:value=operator, type="text", size="8")
+            div{}
+            ValueInput(lable=t!("guidance_overlap"),value=guidance_overlap,unit=Unit::Metre,max=5.0,step=0.05)
+            ValueInput(lable=t!("gap_threshold"),value=gap_threshold,unit=Unit::Metre,max=10.0)
+            CheckboxInput(lable=t!("deck_auto_detect"),value=deck_auto_detect)
+            ValueInput(lable=t!("vibration_threshold"),value=vibration_threshold,unit=Unit::MetrePerSecondSquared,max=50.0)
+            SelectInput(lable=t!("projection"),value=projection)
+            SelectInput(lable=t!("units"),value=units)
+            SelectInput(lable=t!("language"),value=language)
+            (if language.get() != started_in {
+                view! { span(class="settings-hint"){(t!("language_reload"))} }
+            } else {
+                view! {}
+            })
+            CheckboxInput(lable=t!("geo_high_accuracy"),value=geo_high_accuracy)
+            ValueInput(lable=t!("geo_maximum_age"),value=geo_maximum_age,unit=Unit::Second,max=600.0,step=1.0)
+            ValueInput(lable=t!("geo_timeout"),value=geo_timeout,unit=Unit::Second,max=600.0,step=1.0)
+            CheckboxInput(lable=t!("show_last_mowed"),value=show_last_mowed)
+            SelectInput(lable=t!("tile_source"),value=tile_source)
+            (if tile_source.get() == TileSource::Custom {
+                view! {
+                    span{(t!("tile_url"))}
+                    input(bind:value=custom_tile_url, type="url", placeholder="https://…/{z}/{x}/{y}.png")
+                    div{}
+                }
+            } else {
+                view! {}
+            })
+            OfflineTiles(view_bounds=*view_bounds,max_zoom=offline_max_zoom,tile_server=tile_server,tile_source=tile_source)
+            SettingsTransfer(settings=settings)
+            RawValues(raw_visable=raw_visable,menu_visable=menu_visible,projection=projection)
+        }
+                FieldManager(sites=sites,fields=fields,jobs=jobs,active_field=active_field,session=session,coverage=coverage,projection=*projection,gap_threshold=*gap_threshold,operator=*operator)
+                SessionReport(session=*session,analysis=*analysis,mower_width=mower_width)
+        }
             
         }
         main{
             div(class="container"){
-               svg(width="300", height="200", viewbox="0 0 300 200"){
-                desc{"FLagge Homoland"}
-                rect(x="0",y="0",width="100",height="200", fill="#0055a4")
-               }
+                MowMap(coverage=*coverage,analysis=*analysis,field=field,last_mowed=last_mowed,view_bounds=view_bounds,guide_lines=*guide_lines,tile_server=tile_server)
+                Guidance(guidance_line=guidance_line,projection=projection,mower_width=mower_width,overlap=guidance_overlap,guide_lines=guide_lines)
+                DeckButton(engaged=deck_engaged,auto_detect=*deck_auto_detect,vibration_threshold=vibration_threshold,session=*session)
+                Hud(session=*session,coverage=*coverage,analysis=*analysis)
             }
         }
         footer{}
+        (if permissions_open.get() {
+            view! { PermissionScreen(open=permissions_open) }
+        } else {
+            view! {}
+        })
     };
 
+    // record every new fused position
+    let mut vehicle = (None::<i64>, VehicleHeading::default());
+    create_effect(move || {
+        let fused = sensors.fused.get();
+        let Some(pos) = fused.position else {
+            return;
+        };
+        // a new session starts without a known heading
+        let started = session.with_untracked(|s| s.started);
+        if vehicle.0 != Some(started) {
+            vehicle = (Some(started), VehicleHeading::default());
+        }
+        if let Some(direction) = fused.heading() {
+            vehicle.1.update(direction as f64, Utc::now().timestamp_millis());
+        }
+        let machine = profile.get_clone_untracked();
+        let point = TrackPoint::new(
+            WGS84::from(pos),
+            sensors.raw.with_untracked(|raw| raw.accuracy),
+            machine.width,
+            deck_engaged.get_untracked(),
+            vehicle.1.heading(),
+            machine.lever_arm,
+        );
+        let mut recorded = false;
+        session.update(|s| {
+            recorded = s.record(point);
+            if recorded && s.profile.is_none() {
+                s.profile = Some(machine);
+            }
+        });
+        if recorded {
+            coverage.update(|coverage| {
+                let coverage = coverage.get_or_insert_with(|| {
+                    Coverage::new(projection.get_untracked().grid(point.latitude, point.longitude))
+                });
+                if point.engaged {
+                    coverage.add(point.swath_point(&coverage.grid));
+                } else {
+                    coverage.interrupt();
+                }
+            });
+        }
+    });
+
+    // passes are analysed as they are driven, a new session or other settings start over
     spawn_local_scoped(async move {
-        let mut last_pos = ECEF::new(0.0f32, 0.0f32, 0.0f32);
+        let mut analyser = None::<(i64, Projection, PassAnalyser)>;
+        let mut analysed_points = 0;
         loop {
-            if let Some(pos) = get_global_position() {
-                if pos.distance(&last_pos) > 5.0 {
-                    last_pos = pos;
+            let (used_projection, threshold) = (projection.get(), gap_threshold.get());
+            session.with(|s| {
+                let outdated = analyser.as_ref().map_or(true, |(started, used, analyser)| {
+                    *started != s.started || *used != used_projection || analyser.gap_threshold() != threshold
+                });
+                if outdated {
+                    analyser = s
+                        .grid(used_projection)
+                        .map(|grid| (s.started, used_projection, PassAnalyser::new(grid, threshold)));
+                    analysed_points = 0;
+                }
+                match analyser.as_mut() {
+                    Some((_, _, analyser)) if s.points.len() > analysed_points => {
+                        for point in &s.points[analysed_points..] {
+                            analyser.add(point);
+                        }
+                        analysed_points = s.points.len();
+                        analysis.set(Some(analyser.analysis()));
+                    }
+                    None if analysis.with(Option::is_some) => analysis.set(None),
+                    _ => {}
                 }
-            };
-            TimeoutFuture::new(1000).await;
+            });
+            TimeoutFuture::new(5000).await;
         }
     });
 
@@ -1,25 +1,93 @@
-use std::sync::Mutex;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+use crate::utils::log_to_browser;
+
+/// Accesses to any `MutexBox` that failed, shown in the raw data panel.
+static FAILED_ACCESSES: AtomicUsize = AtomicUsize::new(0);
+
+pub fn failed_accesses() -> usize {
+    FAILED_ACCESSES.load(Ordering::Relaxed)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MutexBoxError {
+    /// nothing has been stored yet
+    Uninitialised,
+    /// a closure panicked while holding the lock
+    Poisoned,
+    /// already opened further up the stack, waiting would dead lock the only thread
+    Locked,
+}
+
+impl fmt::Display for MutexBoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MutexBoxError::Uninitialised => write!(f, "value not initialised"),
+            MutexBoxError::Poisoned => write!(f, "lock poisoned by a panic"),
+            MutexBoxError::Locked => write!(f, "value is already locked"),
+        }
+    }
+}
+
+impl std::error::Error for MutexBoxError {}
 
 pub struct MutexBox<T> {
-    pub mutex: Mutex<Option<T>>,
+    mutex: Mutex<Option<T>>,
 }
 
 impl<T> MutexBox<T> {
-    #[allow(dead_code)]
     pub const fn new() -> Self {
         let mutex: Mutex<Option<T>> = Mutex::new(None);
         MutexBox {
-            mutex: mutex,
+            mutex,
         }
     }
-    #[allow(dead_code)]
     pub const fn new_inited(data:T)->Self{
         let mutex: Mutex<Option<T>> = Mutex::new(Some(data));
         Self {
-            mutex: mutex,
+            mutex,
+        }
+    }
+
+    fn failed<R>(error: MutexBoxError) -> Result<R, MutexBoxError> {
+        FAILED_ACCESSES.fetch_add(1, Ordering::Relaxed);
+        Err(error)
+    }
+
+    /// The lock, even if poisoned, when `recover` is set.
+    fn lock(&self, recover: bool) -> Result<MutexGuard<'_, Option<T>>, MutexBoxError> {
+        match self.mutex.try_lock() {
+            Ok(guard) => Ok(guard),
+            Err(TryLockError::WouldBlock) => Self::failed(MutexBoxError::Locked),
+            Err(TryLockError::Poisoned(poisoned)) if recover => {
+                FAILED_ACCESSES.fetch_add(1, Ordering::Relaxed);
+                self.mutex.clear_poison();
+                Ok(poisoned.into_inner())
+            }
+            Err(TryLockError::Poisoned(_)) => Self::failed(MutexBoxError::Poisoned),
+        }
+    }
+
+    /// Runs `found` on the value, without recovering from a poisoned lock.
+    pub fn try_open_locked<FunctionLocked, TypeReturn>(
+        &self,
+        found: FunctionLocked,
+    ) -> Result<TypeReturn, MutexBoxError>
+    where
+        FunctionLocked: FnOnce(&mut T) -> TypeReturn,
+    {
+        match self.lock(false)?.as_mut() {
+            Some(handler) => Ok(found(handler)),
+            None => Self::failed(MutexBoxError::Uninitialised),
         }
     }
-    #[allow(dead_code)]
+
+    /// Runs `found` on the value, `error_val` if there is none or it is locked already.
+    ///
+    /// A poisoned lock is recovered, the sensor values in here are overwritten by the
+    /// next reading anyway.
     pub fn open_locked<FunctionLocked, TypeReturn>(
         &self,
         found: FunctionLocked,
@@ -28,22 +96,61 @@ impl<T> MutexBox<T> {
     where
         FunctionLocked: FnOnce(&mut T) -> TypeReturn,
     {
-        if let Ok(mut handler_option) = self.mutex.lock() {
-            if let Some(handler) = handler_option.as_mut() {
-                found(handler)
-            } else {
-                error_val
-            }
-        } else {
+        let result = match self.lock(true) {
+            Ok(mut handler_option) => match handler_option.as_mut() {
+                Some(handler) => Ok(found(handler)),
+                None => Self::failed(MutexBoxError::Uninitialised),
+            },
+            Err(error) => Err(error),
+        };
+        result.unwrap_or_else(|error| {
+            log_to_browser(format!("MutexBox access failed: {}", error));
             error_val
-        }
+        })
+    }
+
+    /// Stores `data` and returns the previous value, this also recovers a poisoned lock.
+    pub fn replace(&self, data: T) -> Result<Option<T>, MutexBoxError> {
+        Ok(self.lock(true)?.replace(data))
+    }
+
+    /// Removes the value, the box is uninitialised afterwards.
+    pub fn take(&self) -> Result<Option<T>, MutexBoxError> {
+        Ok(self.lock(true)?.take())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uninitialised_until_stored() {
+        let value: MutexBox<u32> = MutexBox::new();
+        assert_eq!(value.try_open_locked(|value| *value), Err(MutexBoxError::Uninitialised));
+        assert_eq!(value.replace(1), Ok(None));
+        assert_eq!(value.try_open_locked(|value| *value), Ok(1));
+        assert_eq!(value.replace(2), Ok(Some(1)));
+        assert_eq!(value.take(), Ok(Some(2)));
+        assert_eq!(value.take(), Ok(None));
+        assert_eq!(value.open_locked(|value| *value, 0), 0);
+    }
+
+    #[test]
+    fn locked_further_up_the_stack() {
+        let value = MutexBox::new_inited(1);
+        let inner = value.try_open_locked(|_| value.try_open_locked(|value| *value));
+        assert_eq!(inner, Ok(Err(MutexBoxError::Locked)));
+        assert_eq!(value.open_locked(|_| value.open_locked(|value| *value, 0), 5), 0);
     }
 
-    #[allow(dead_code)]
-    pub fn init(&self,data:T) {
-        self.mutex
-            .lock()
-            .expect(format!("could not lock ").as_str())
-            .get_or_insert(data);
+    #[test]
+    fn poisoned_lock_is_recovered() {
+        let value = MutexBox::new_inited(1);
+        let panicked = std::panic::catch_unwind(|| value.try_open_locked(|_| panic!("reading failed")));
+        assert!(panicked.is_err());
+        assert_eq!(value.try_open_locked(|value| *value), Err(MutexBoxError::Poisoned));
+        assert_eq!(value.open_locked(|value| *value, 0), 1);
+        assert_eq!(value.try_open_locked(|value| *value), Ok(1));
     }
 }
@@ -1,51 +1,300 @@
-use crate::{mutex_box::MutexBox, utils::log_to_browser};
+use crate::{mutex_box::MutexBox, profile::EskfPreset, utils::log_to_browser};
+use std::{cell::RefCell, rc::Rc};
+use js_sys::{Function, Promise, Reflect};
 use chrono::{Datelike, NaiveDateTime, Timelike, Utc};
 use libgeomag::{DateTime, GeodeticLocation, ModelExt, IGRF, WMM};
 use nalgebra::{Vector2, Vector3, Rotation3};
 use nav_types::{ECEF, WGS84};
+use sycamore::prelude::*;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::{self, closure::Closure, JsCast};
-use web_sys::{window, DeviceMotionEvent, DeviceOrientationEvent, Geolocation, Position, Window};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    window, DeviceMotionEvent, DeviceOrientationEvent, Geolocation, Position, PositionError,
+    PositionOptions, Window,
+};
 mod position_fusion;
+mod vibration;
 use position_fusion::PositionFusion;
+use vibration::VibrationDetector;
 
 #[wasm_bindgen]
 extern "C" {
     fn alert(s: &str);
 }
 
-static POSITION_FUSION: MutexBox<PositionFusion> = MutexBox::new_inited(PositionFusion::new());
-static RAW_VALUES: MutexBox<RawValues> = MutexBox::new_inited(RawValues::new());
+/// Below this ground speed in m/s the direction of travel is too noisy to use.
+pub const MIN_HEADING_SPEED: f32 = 0.3;
 
-pub fn start_web_data() {
+/// Missed GNSS fixes after which the fix rate drops to 0.
+const STALE_FIXES: f32 = 3.0;
+
+static GEOLOCATION_OPTIONS: MutexBox<GeolocationOptions> =
+    MutexBox::new_inited(GeolocationOptions::new());
+static WATCH_ID: MutexBox<i32> = MutexBox::new();
+
+thread_local! {
+    /// Success and error callback of `watchPosition`.
+    static GEOLOCATION_CALLBACKS: RefCell<Option<(Function, Function)>> = const { RefCell::new(None) };
+}
+
+/// Whether the browser delivers data of a sensor.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SensorStatus {
+    /// not asked yet or no data so far
+    #[default]
+    Pending,
+    Granted,
+    Denied,
+    /// missing from the browser or silent since the start
+    Unsupported,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sensor {
+    Location,
+    Motion,
+    Orientation,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SensorStatuses {
+    pub location: SensorStatus,
+    pub motion: SensorStatus,
+    pub orientation: SensorStatus,
+}
+
+impl SensorStatuses {
+    pub const fn new() -> Self {
+        SensorStatuses {
+            location: SensorStatus::Pending,
+            motion: SensorStatus::Pending,
+            orientation: SensorStatus::Pending,
+        }
+    }
+
+    pub fn get(&self, sensor: Sensor) -> SensorStatus {
+        match sensor {
+            Sensor::Location => self.location,
+            Sensor::Motion => self.motion,
+            Sensor::Orientation => self.orientation,
+        }
+    }
+
+    fn get_mut(&mut self, sensor: Sensor) -> &mut SensorStatus {
+        match sensor {
+            Sensor::Location => &mut self.location,
+            Sensor::Motion => &mut self.motion,
+            Sensor::Orientation => &mut self.orientation,
+        }
+    }
+}
+
+/// Asks for the motion and orientation sensors where the browser requires it (iOS).
+///
+/// Has to be called from a user gesture, the returned future resolves once the
+/// user answered.
+pub fn request_motion_permission(store: &SensorStore) -> impl std::future::Future<Output = ()> {
+    let requests: Vec<(Sensor, Promise)> = [
+        ("DeviceMotionEvent", Sensor::Motion),
+        ("DeviceOrientationEvent", Sensor::Orientation),
+    ]
+    .into_iter()
+    .filter_map(|(event, sensor)| {
+        let constructor = window()
+            .and_then(|win| Reflect::get(&win, &JsValue::from_str(event)).ok())
+            .filter(|constructor| !constructor.is_undefined());
+        let Some(constructor) = constructor else {
+            store.set_sensor_status(sensor, SensorStatus::Unsupported);
+            return None;
+        };
+        // only Safari wants to be asked, everywhere else the events just arrive
+        Reflect::get(&constructor, &JsValue::from_str("requestPermission"))
+            .ok()
+            .and_then(|request| request.dyn_into::<Function>().ok())
+            .and_then(|request| request.call0(&constructor).ok())
+            .and_then(|promise| promise.dyn_into::<Promise>().ok())
+            .map(|promise| (sensor, promise))
+    })
+    .collect();
+
+    let store = store.clone();
+    async move {
+        for (sensor, promise) in requests {
+            let granted = JsFuture::from(promise)
+                .await
+                .ok()
+                .and_then(|answer| answer.as_string())
+                .is_some_and(|answer| answer == "granted");
+            store.set_sensor_status(
+                sensor,
+                if granted {
+                    SensorStatus::Granted
+                } else {
+                    SensorStatus::Denied
+                },
+            );
+        }
+    }
+}
+
+/// Output of the position fusion.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FusedState {
+    pub position: Option<ECEF<f32>>,
+    /// east/north velocity in m/s
+    pub velocity: Option<Vector2<f32>>,
+}
+
+impl FusedState {
+    /// Direction of travel in radians clockwise from north.
+    ///
+    /// `None` while standing still, as the direction is just noise then.
+    pub fn heading(&self) -> Option<f32> {
+        self.velocity
+            .filter(|velocity| velocity.norm() > MIN_HEADING_SPEED)
+            .map(|velocity| velocity[0].atan2(velocity[1]))
+    }
+}
+
+/// The latest sensor readings and the fused position, shared through the context.
+///
+/// Sensor callbacks run on the one browser thread and write the signals directly,
+/// so subscribers react to every reading instead of polling.
+#[derive(Clone)]
+pub struct SensorStore {
+    pub raw: Signal<RawValues>,
+    pub fused: Signal<FusedState>,
+    pub status: Signal<SensorStatuses>,
+    vibration: Signal<VibrationDetector>,
+    fusion: Rc<RefCell<PositionFusion>>,
+}
+
+impl SensorStore {
+    pub fn new() -> Self {
+        SensorStore {
+            raw: create_signal(RawValues::new()),
+            fused: create_signal(FusedState::default()),
+            status: create_signal(SensorStatuses::new()),
+            vibration: create_signal(VibrationDetector::new()),
+            fusion: Rc::new(RefCell::new(PositionFusion::new())),
+        }
+    }
+
+    /// Changes the fusion and hands the new state to the subscribers.
+    fn update_fusion(&self, update: impl FnOnce(&mut PositionFusion)) {
+        let state = {
+            let mut fusion = self.fusion.borrow_mut();
+            update(&mut fusion);
+            FusedState {
+                position: fusion.get_global_position(),
+                velocity: fusion.get_velocity(),
+            }
+        };
+        self.fused.set(state);
+    }
+
+    fn predict(&self) {
+        let raw = self.raw.get_untracked();
+        let predicted = self
+            .fusion
+            .borrow_mut()
+            .predict(raw.acceleration, raw.orientation);
+        if predicted {
+            self.update_fusion(|_| {});
+        }
+    }
+
+    pub fn position(&self) -> Option<ECEF<f32>> {
+        self.fused.with(|fused| fused.position)
+    }
+
+    pub fn heading(&self) -> Option<f32> {
+        self.fused.with(FusedState::heading)
+    }
+
+    pub fn set_fusion_preset(&self, preset: EskfPreset) {
+        let (position_variance, velocity_variance) = preset.variances();
+        self.fusion
+            .borrow_mut()
+            .set_variances(position_variance, velocity_variance);
+    }
+
+    /// Drops the fused state, the next GNSS fix starts the filter afresh.
+    pub fn reset(&self) {
+        self.update_fusion(PositionFusion::reset);
+    }
+
+    fn set_sensor_status(&self, sensor: Sensor, value: SensorStatus) {
+        self.status.update(|status| *status.get_mut(sensor) = value);
+    }
+
+    /// Marks a sensor as delivering data, unless the user was already asked and said no.
+    ///
+    /// Late data also clears a time out of [`SensorStore::mark_silent_sensors`]. Only
+    /// a change is sent to the subscribers, not every reading.
+    fn sensor_delivers(&self, sensor: Sensor) {
+        let waiting = self.status.with_untracked(|status| {
+            matches!(status.get(sensor), SensorStatus::Pending | SensorStatus::Unsupported)
+        });
+        if waiting {
+            self.set_sensor_status(sensor, SensorStatus::Granted);
+        }
+    }
+
+    /// Marks the motion and orientation sensors that have not sent anything yet as
+    /// unsupported, some browsers offer the events on devices without the hardware.
+    ///
+    /// The location is left alone, a first GNSS fix can take minutes.
+    pub fn mark_silent_sensors(&self) {
+        for sensor in [Sensor::Motion, Sensor::Orientation] {
+            if self.status.with_untracked(|status| status.get(sensor)) == SensorStatus::Pending {
+                self.set_sensor_status(sensor, SensorStatus::Unsupported);
+            }
+        }
+    }
+}
+
+impl Default for SensorStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn use_sensor_store() -> SensorStore {
+    use_context::<SensorStore>()
+}
+
+pub fn start_web_data(store: &SensorStore) {
     if let Some(win) = window() {
         if let Ok(geoloc) = win.navigator().geolocation() {
-            get_geoloc(&geoloc);
+            get_geoloc(&geoloc, store.clone());
+        } else {
+            store.set_sensor_status(Sensor::Location, SensorStatus::Unsupported);
         }
-        get_acceleation(&win);
-        get_device_orientation(&win);
+        get_acceleation(&win, store.clone());
+        get_device_orientation(&win, store.clone());
     }
 }
 
-fn get_acceleation(win: &Window) {
+fn get_acceleation(win: &Window, store: SensorStore) {
     let cb: Closure<dyn Fn(DeviceMotionEvent)> = Closure::new(move |data: DeviceMotionEvent| {
         if let Some(acc) = data.acceleration_including_gravity() {
+            store.sensor_delivers(Sensor::Motion);
             //log_to_browser("ACC-Data".to_string());
             if let (Some(x), Some(y), Some(z)) = (acc.x(), acc.y(), acc.z()) {
                 let acc_vec = Vector3::new(x as f32, y as f32, z as f32);
+                let interval = data.interval().map(|ms| (ms / 1000.0) as f32);
+                store
+                    .vibration
+                    .update(|detector| detector.push(acc_vec.norm(), interval));
+                let vibration = store.vibration.with_untracked(VibrationDetector::level);
 
-                RAW_VALUES.open_locked(
-                    |raw| {
-                        raw.acceleration = acc_vec;
-                        POSITION_FUSION.open_locked(
-                            |pos| {
-                                pos.predict(raw.acceleration, raw.orientation)
-                            },
-                            (),
-                        );
-                    },
-                    (),
-                );
+                store.raw.update(|raw| {
+                    raw.acceleration = acc_vec;
+                    raw.vibration = vibration;
+                });
+                store.predict();
 
                 //log_to_browser(format!("acc: {}/{}/{}", x, y, z));
             }
@@ -55,9 +304,12 @@ fn get_acceleation(win: &Window) {
     cb.forget();
 }
 
-fn get_geoloc(geoloc: &Geolocation) {
+fn get_geoloc(geoloc: &Geolocation, store: SensorStore) {
+    let error_store = store.clone();
     let cb: Closure<dyn Fn(Position)> = Closure::new(move |data: Position| {
+        store.sensor_delivers(Sensor::Location);
         let coords = data.coords();
+        let accuracy = coords.accuracy() as f32;
         let speed = coords.speed();
         let heading = coords.heading();
         let wgs = WGS84::from_degrees_and_meters(
@@ -81,48 +333,130 @@ fn get_geoloc(geoloc: &Geolocation) {
             velocity = Some(Vector2::new(speed_e as f32, speed_n as f32));
         }
         //alert(format!("long: {}\nlat: {}\nspeed: {:?}\nheading: {:?}",wgs.longitude_degrees(),wgs.latitude_degrees(),speed,heading).as_str());
-        RAW_VALUES.open_locked(
-            |raw| {
-                raw.position = Some(coords.clone());
-                raw.magnetic_declination = magnetic_declination;
+        store.raw.update(|raw| {
+            raw.position = Some(coords.clone());
+            raw.accuracy = accuracy;
+            raw.record_fix(Utc::now().timestamp_millis());
+            raw.magnetic_declination = magnetic_declination;
 
-                if let Some(vel) = velocity {
-                    raw.velocity = vel.clone();
-                }
-            },
-            (),
-        );
+            if let Some(vel) = velocity {
+                raw.velocity = vel.clone();
+            }
+        });
 
-        POSITION_FUSION.open_locked(
-            |pos| {
-                pos.update_global_position(coords, velocity);
-            },
-            (),
-        );
+        store.update_fusion(|pos| pos.update_global_position(coords, velocity));
     });
-    if let Ok(_pos) = geoloc.watch_position(cb.as_ref().unchecked_ref()) {}
-    cb.forget();
+    let error_cb: Closure<dyn Fn(PositionError)> = Closure::new(move |error: PositionError| {
+        log_to_browser(format!("Geolocation error {}: {}", error.code(), error.message()));
+        error_store.raw.update(|raw| raw.position_error = Some(error.code()));
+        if error.code() == PositionError::PERMISSION_DENIED {
+            error_store.set_sensor_status(Sensor::Location, SensorStatus::Denied);
+        }
+    });
+    // kept for watching again with other options
+    GEOLOCATION_CALLBACKS.with(|callbacks| {
+        *callbacks.borrow_mut() = Some((
+            cb.into_js_value().unchecked_into(),
+            error_cb.into_js_value().unchecked_into(),
+        ))
+    });
+    watch_geoloc(geoloc);
 }
 
-fn get_device_orientation(win: &Window) {
+/// (Re)starts watching the position with the current [`GeolocationOptions`].
+fn watch_geoloc(geoloc: &Geolocation) {
+    // without the old id a second watch would run alongside the first one for good
+    match WATCH_ID.take() {
+        Ok(Some(id)) => geoloc.clear_watch(id),
+        Ok(None) => {}
+        Err(error) => {
+            log_to_browser(format!("Position watch not restarted: {}", error));
+            return;
+        }
+    }
+    let options = GEOLOCATION_OPTIONS.open_locked(|options| *options, GeolocationOptions::new());
+    GEOLOCATION_CALLBACKS.with(|callbacks| {
+        if let Some((success, error)) = callbacks.borrow().as_ref() {
+            if let Ok(id) = geoloc.watch_position_with_error_callback_and_options(
+                success,
+                Some(error),
+                &options.to_position_options(),
+            ) {
+                if let Err(error) = WATCH_ID.replace(id) {
+                    // a watch that cannot be stopped later is not started at all
+                    log_to_browser(format!("Position watch not stored: {}", error));
+                    geoloc.clear_watch(id);
+                }
+            }
+        }
+    });
+}
+
+/// Settings passed on to `watchPosition`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GeolocationOptions {
+    pub high_accuracy: bool,
+    /// oldest cached position accepted in milliseconds
+    pub maximum_age: u32,
+    /// longest wait for a fix in milliseconds, `None` to wait forever
+    pub timeout: Option<u32>,
+}
+
+impl GeolocationOptions {
+    pub const fn new() -> Self {
+        GeolocationOptions {
+            high_accuracy: true,
+            maximum_age: 0,
+            timeout: None,
+        }
+    }
+
+    fn to_position_options(self) -> PositionOptions {
+        let mut options = PositionOptions::new();
+        options.enable_high_accuracy(self.high_accuracy);
+        options.maximum_age(self.maximum_age);
+        if let Some(timeout) = self.timeout {
+            options.timeout(timeout);
+        }
+        options
+    }
+}
+
+impl Default for GeolocationOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Uses `options` from now on, a running watch is restarted with them.
+pub fn set_geolocation_options(options: GeolocationOptions) {
+    let changed = match GEOLOCATION_OPTIONS.replace(options) {
+        Ok(previous) => previous != Some(options),
+        Err(error) => {
+            log_to_browser(format!("Position options not changed: {}", error));
+            false
+        }
+    };
+    // restarts a running watch, before the sensors are started there are no callbacks
+    if changed {
+        if let Some(Ok(geoloc)) = window().map(|win| win.navigator().geolocation()) {
+            watch_geoloc(&geoloc);
+        }
+    }
+}
+
+fn get_device_orientation(win: &Window, store: SensorStore) {
     let cb: Closure<dyn Fn(DeviceOrientationEvent)> =
         Closure::new(move |data: DeviceOrientationEvent| {
             if let (Some(x), Some(y), Some(z)) = (data.alpha(), data.beta(), data.gamma()) {
+                store.sensor_delivers(Sensor::Orientation);
                 let mut orientation_vec = Vector3::new(x.to_radians() as f32 , y.to_radians() as f32, z.to_radians() as f32);
 
-                RAW_VALUES.open_locked(
-                    |raw| {
-                        orientation_vec[0]=orientation_vec[0]-raw.magnetic_declination;
-                        raw.orientation = orientation_vec;
-                        POSITION_FUSION.open_locked(
-                            |pos| {
-                                pos.predict(raw.acceleration, raw.orientation)
-                            },
-                            (),
-                        );
-                    },
-                    (),
-                );
+                store.raw.update(|raw| {
+                    orientation_vec[0]=orientation_vec[0]-raw.magnetic_declination;
+                    raw.orientation = orientation_vec;
+                });
+                store.predict();
                 //log_to_browser(format!("ori: {}/{}/{}", x, y, z));
             }
         });
@@ -131,14 +465,6 @@ fn get_device_orientation(win: &Window) {
     cb.forget();
 }
 
-pub fn get_global_position() -> Option<ECEF<f32>> {
-    POSITION_FUSION.open_locked(|pos| pos.get_global_position().clone(), None)
-}
-#[allow(dead_code)]
-pub fn reset() {
-    POSITION_FUSION.open_locked(|pos| pos.reset(), ())
-}
-
 pub fn calc_magnetic_declination(pos: WGS84<f32>, time: NaiveDateTime) -> f32 {
     let l = GeodeticLocation::new(
         pos.longitude_degrees() as f64,
@@ -163,10 +489,6 @@ pub fn calc_magnetic_declination(pos: WGS84<f32>, time: NaiveDateTime) -> f32 {
     ((m1 + m2) / 2.0).to_radians() as f32
 }
 
-pub fn get_raw_data() -> RawValues {
-    RAW_VALUES.open_locked(|raw| raw.clone(), RawValues::new())
-}
-
 #[derive(Clone, Copy, Debug)]
 pub struct RawValues {
     pub position: Option<ECEF<f32>>,
@@ -174,6 +496,15 @@ pub struct RawValues {
     pub orientation: Vector3<f32>,
     pub acceleration: Vector3<f32>,
     pub magnetic_declination: f32,
+    pub accuracy: f32,
+    /// RMS of the engine band of the acceleration in m/s²
+    pub vibration: f32,
+    /// unix time in milliseconds of the last GNSS fix
+    pub last_fix: Option<i64>,
+    /// smoothed time between GNSS fixes in milliseconds
+    pub fix_interval: Option<f32>,
+    /// `PositionError` code of the last failed fix, cleared by the next good one
+    pub position_error: Option<u16>,
 }
 
 impl RawValues {
@@ -184,7 +515,39 @@ impl RawValues {
             orientation: Vector3::new(0.0, 0.0, 0.0),
             acceleration: Vector3::new(0.0, 0.0, 0.0),
             magnetic_declination: 0.0,
+            accuracy: 0.0,
+            vibration: 0.0,
+            last_fix: None,
+            fix_interval: None,
+            position_error: None,
+        }
+    }
+
+    fn record_fix(&mut self, time: i64) {
+        if let Some(last_fix) = self.last_fix {
+            let interval = (time - last_fix) as f32;
+            self.fix_interval = Some(match self.fix_interval {
+                Some(smoothed) => smoothed * 0.8 + interval * 0.2,
+                None => interval,
+            });
+        }
+        self.last_fix = Some(time);
+        self.position_error = None;
+    }
+
+    /// GNSS fixes per second at unix time `now` in milliseconds.
+    ///
+    /// Falls while the next fix is overdue and is 0 once [`STALE_FIXES`] fixes
+    /// in a row are missing.
+    pub fn fix_rate(&self, now: i64) -> f32 {
+        let (Some(last_fix), Some(interval)) = (self.last_fix, self.fix_interval) else {
+            return 0.0;
+        };
+        let waiting = (now - last_fix) as f32;
+        if interval <= 0.0 || waiting > interval * STALE_FIXES {
+            return 0.0;
         }
+        1000.0 / interval.max(waiting)
     }
 
     pub fn get_acceleration(self)->Vector3<f32> {
@@ -213,3 +576,25 @@ impl RawValues {
         a_world
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fix_rate_of_missing_fixes() {
+        let mut raw = RawValues::new();
+        assert_eq!(raw.fix_rate(0), 0.0);
+        raw.record_fix(0);
+        assert_eq!(raw.fix_rate(100), 0.0);
+        for time in [200, 400, 600] {
+            raw.record_fix(time);
+        }
+        assert_eq!(raw.fix_rate(600), 5.0);
+        assert_eq!(raw.fix_rate(800), 5.0);
+        // overdue fixes lower the rate until it gives up
+        assert_eq!(raw.fix_rate(1000), 2.5);
+        assert_eq!(raw.fix_rate(1100), 2.0);
+        assert_eq!(raw.fix_rate(1201), 0.0);
+    }
+}
@@ -9,7 +9,8 @@ pub(super) struct PositionFusion {
     reference_position: Option<ECEF<f32>>,
     kalman_filter: Option<ESKF>,
     last_prediction:Option<Instant>,
-
+    position_variance: f32,
+    velocity_variance: f32,
 }
 
 impl PositionFusion {
@@ -18,9 +19,16 @@ impl PositionFusion {
             reference_position: None,
             kalman_filter: None,
             last_prediction: None,
+            position_variance: 0.1,
+            velocity_variance: 0.1,
         }
     }
 
+    pub(super) fn set_variances(&mut self, position_variance: f32, velocity_variance: f32) {
+        self.position_variance = position_variance;
+        self.velocity_variance = velocity_variance;
+    }
+
     pub(super) fn update_global_position(
         &mut self,
         pos: ECEF<f32>,
@@ -31,16 +39,22 @@ impl PositionFusion {
         {
             let rel_pos = ref_pos - pos;
             let rel_pos = Point3::new(rel_pos.east(), rel_pos.north(), rel_pos.up());
-            observe_position(&mut kalman, rel_pos, 0.1, velocity_2d, 0.1);
+            observe_position(
+                &mut kalman,
+                rel_pos,
+                self.position_variance,
+                velocity_2d,
+                self.velocity_variance,
+            );
             (Some(ref_pos), Some(kalman))
         } else {
             let mut kalman = Builder::new().build();
             observe_position(
                 &mut kalman,
                 Point3::new(0.0f32, 0.0, 0.0),
-                0.1,
+                self.position_variance,
                 velocity_2d,
-                0.1,
+                self.velocity_variance,
             );
             (Some(pos), Some(kalman))
         };
@@ -61,12 +75,22 @@ impl PositionFusion {
         }
     }
 
-    pub(super) fn predict( &mut self,acceleration:Vector3<f32>,rotation:Vector3<f32>){
+    /// Fused east/north velocity in m/s.
+    pub(super) fn get_velocity(&self) -> Option<Vector2<f32>> {
+        self.kalman_filter
+            .map(|kalman| Vector2::new(kalman.velocity[0], kalman.velocity[1]))
+    }
+
+    /// Returns whether the filter was moved on.
+    pub(super) fn predict( &mut self,acceleration:Vector3<f32>,rotation:Vector3<f32>) -> bool {
 
         if let (Some(mut kalman),Some(last_prediction) )= (self.kalman_filter,self.last_prediction)  {
             let delta=Instant::now()- last_prediction;
             self.last_prediction=Some(Instant::now());
-            kalman.predict(acceleration, rotation, delta)
+            kalman.predict(acceleration, rotation, delta);
+            true
+        } else {
+            false
         }
     }
 
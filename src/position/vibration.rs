@@ -0,0 +1,140 @@
+use std::collections::VecDeque;
+use std::f32::consts::TAU;
+
+/// Number of acceleration samples analysed at once.
+const WINDOW: usize = 64;
+/// Vibration below this frequency in Hz is driving motion, not the engine.
+const MIN_FREQUENCY: f32 = 8.0;
+/// Sample interval assumed until the browser reports one, in seconds.
+const DEFAULT_INTERVAL: f32 = 1.0 / 60.0;
+
+/// Estimates the engine/blade vibration from the `devicemotion` acceleration stream.
+///
+/// Sampling is usually at 60 Hz, so blade frequencies are aliased; all energy above
+/// [`MIN_FREQUENCY`] is counted, which is enough to tell a running deck from transit.
+pub(super) struct VibrationDetector {
+    samples: VecDeque<f32>,
+    interval: f32,
+    level: f32,
+    since_update: usize,
+}
+
+impl VibrationDetector {
+    pub(super) const fn new() -> Self {
+        VibrationDetector {
+            samples: VecDeque::new(),
+            interval: DEFAULT_INTERVAL,
+            level: 0.0,
+            since_update: 0,
+        }
+    }
+
+    /// Adds the magnitude of one acceleration sample, `interval` in seconds.
+    pub(super) fn push(&mut self, magnitude: f32, interval: Option<f32>) {
+        if let Some(interval) = interval.filter(|i| *i > 0.0) {
+            self.interval = interval;
+        }
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(magnitude);
+        self.since_update += 1;
+        if self.samples.len() == WINDOW && self.since_update >= WINDOW / 4 {
+            self.since_update = 0;
+            self.level = self.band_rms();
+        }
+    }
+
+    /// RMS acceleration above [`MIN_FREQUENCY`] of the last analysed window in m/s².
+    pub(super) fn level(&self) -> f32 {
+        self.level
+    }
+
+    fn band_rms(&self) -> f32 {
+        let n = self.samples.len();
+        let mean = self.samples.iter().sum::<f32>() / n as f32;
+        let resolution = 1.0 / (self.interval * n as f32);
+        let first_bin = ((MIN_FREQUENCY / resolution).ceil() as usize).max(1);
+
+        let mut power = 0.0;
+        for bin in first_bin..=n / 2 {
+            let (mut re, mut im) = (0.0f32, 0.0f32);
+            for (i, sample) in self.samples.iter().enumerate() {
+                let angle = TAU * (bin * i) as f32 / n as f32;
+                re += (sample - mean) * angle.cos();
+                im -= (sample - mean) * angle.sin();
+            }
+            // bins below Nyquist stand for their negative frequency as well
+            let weight = if bin * 2 == n { 1.0 } else { 2.0 };
+            power += weight * (re * re + im * im);
+        }
+        (power.sqrt()) / n as f32
+    }
+}
+
+impl Default for VibrationDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 60.0;
+
+    /// Acceleration magnitudes of gravity plus a sine of `frequency` Hz.
+    fn feed(detector: &mut VibrationDetector, frequency: f32, amplitude: f32, count: usize) {
+        for i in 0..count {
+            let time = i as f32 / RATE;
+            let magnitude = 9.81 + amplitude * (TAU * frequency * time).sin();
+            detector.push(magnitude, Some(1.0 / RATE));
+        }
+    }
+
+    #[test]
+    fn nothing_before_the_window_is_full() {
+        let mut detector = VibrationDetector::new();
+        feed(&mut detector, 15.0, 2.0, WINDOW - 1);
+        assert_eq!(detector.level(), 0.0);
+        feed(&mut detector, 15.0, 2.0, 1);
+        assert!(detector.level() > 0.0);
+    }
+
+    #[test]
+    fn engine_vibration() {
+        let mut detector = VibrationDetector::new();
+        // 15 Hz falls on a frequency bin, so all of it is found
+        feed(&mut detector, 15.0, 2.0, 2 * WINDOW);
+        assert!((detector.level() - 2.0 / 2f32.sqrt()).abs() < 0.01);
+    }
+
+    #[test]
+    fn driving_motion_is_ignored() {
+        let mut detector = VibrationDetector::new();
+        feed(&mut detector, 1.0, 2.0, 2 * WINDOW);
+        assert!(detector.level() < 0.1);
+        let mut detector = VibrationDetector::new();
+        feed(&mut detector, 0.0, 0.0, 2 * WINDOW);
+        assert!(detector.level().abs() < 1e-4);
+    }
+
+    #[test]
+    fn sample_interval() {
+        // at 20 Hz the band starts at the Nyquist frequency of 10 Hz
+        let mut detector = VibrationDetector::new();
+        for i in 0..2 * WINDOW {
+            let sign = if i % 2 == 0 { 1.0 } else { -1.0 };
+            detector.push(9.81 + sign, Some(0.05));
+        }
+        assert!((detector.level() - 1.0).abs() < 0.01);
+        // 5 Hz is below the band
+        let mut detector = VibrationDetector::new();
+        for i in 0..2 * WINDOW {
+            let sign = if i % 4 < 2 { 1.0 } else { -1.0 };
+            detector.push(9.81 + sign, Some(0.05));
+        }
+        assert!(detector.level() < 0.01);
+    }
+}
@@ -0,0 +1,80 @@
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+
+use crate::components::SelectOption;
+use crate::geometry::LeverArm;
+
+/// GNSS observation variances of the position fusion for a kind of machine, the IMU
+/// process noise is the same for all.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EskfPreset {
+    /// slow and bumpy, the GNSS velocity gets the most weight
+    PushMower,
+    #[default]
+    RideOn,
+    /// fast and smooth, GNSS fixes get less weight against the IMU prediction
+    Tractor,
+}
+
+impl EskfPreset {
+    /// Observation variances of GNSS position (m²) and velocity ((m/s)²).
+    pub fn variances(&self) -> (f32, f32) {
+        match self {
+            EskfPreset::PushMower => (0.1, 0.05),
+            EskfPreset::RideOn => (0.1, 0.1),
+            EskfPreset::Tractor => (0.2, 0.2),
+        }
+    }
+}
+
+impl SelectOption for EskfPreset {
+    fn all() -> Vec<Self> {
+        vec![EskfPreset::PushMower, EskfPreset::RideOn, EskfPreset::Tractor]
+    }
+
+    fn lable(&self) -> String {
+        match self {
+            EskfPreset::PushMower => t!("preset_push_mower"),
+            EskfPreset::RideOn => t!("preset_ride_on"),
+            EskfPreset::Tractor => t!("preset_tractor"),
+        }
+    }
+}
+
+/// Settings of one machine of the fleet.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MowerProfile {
+    pub name: String,
+    /// cut width in metres
+    pub width: f64,
+    pub lever_arm: LeverArm,
+    pub eskf_preset: EskfPreset,
+    /// usual working speed in m/s
+    pub typical_speed: f64,
+}
+
+impl MowerProfile {
+    pub fn new(name: String) -> Self {
+        MowerProfile {
+            name,
+            width: 0.5,
+            lever_arm: LeverArm::default(),
+            eskf_preset: EskfPreset::default(),
+            typical_speed: 1.5,
+        }
+    }
+
+    /// Puts values that are no use for mowing back to the defaults.
+    pub fn validate(&mut self) {
+        let defaults = MowerProfile::new(String::new());
+        if !(self.width > 0.0 && self.width.is_finite()) {
+            self.width = defaults.width;
+        }
+        if !(self.typical_speed > 0.0 && self.typical_speed.is_finite()) {
+            self.typical_speed = defaults.typical_speed;
+        }
+        if !(self.lever_arm.forward.is_finite() && self.lever_arm.lateral.is_finite()) {
+            self.lever_arm = defaults.lever_arm;
+        }
+    }
+}
@@ -0,0 +1,572 @@
+use nav_types::WGS84;
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+
+use crate::components::SelectOption;
+
+/// Grid the fused positions are displayed, measured and exported in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Projection {
+    /// WGS84 / UTM, zone picked from the position.
+    #[default]
+    Utm,
+    /// ETRS89 / UTM zone 32N (EPSG:25832).
+    Etrs89Utm32,
+    /// DHDN / Gauss-Krüger, 3° zones (EPSG:31466-31469).
+    GaussKrueger,
+}
+
+impl SelectOption for Projection {
+    fn all() -> Vec<Self> {
+        vec![
+            Projection::Utm,
+            Projection::Etrs89Utm32,
+            Projection::GaussKrueger,
+        ]
+    }
+
+    fn lable(&self) -> String {
+        match self {
+            Projection::Utm => t!("projection_utm"),
+            Projection::Etrs89Utm32 => t!("projection_etrs89_utm32"),
+            Projection::GaussKrueger => t!("projection_gauss_krueger"),
+        }
+    }
+}
+
+impl Projection {
+    /// Grid system this projection uses around the given position.
+    ///
+    /// The zone is fixed by the returned [`GridSystem`], so a session that keeps
+    /// it will not jump when crossing a zone border.
+    pub fn grid(&self, latitude: f64, longitude: f64) -> GridSystem {
+        match self {
+            Projection::Utm => {
+                let zone = utm_zone(latitude, longitude);
+                GridSystem {
+                    ellipsoid: WGS84_ELLIPSOID,
+                    central_meridian: (zone as f64 * 6.0 - 183.0).to_radians(),
+                    scale: 0.9996,
+                    false_easting: 500_000.0,
+                    false_northing: if latitude < 0.0 { 10_000_000.0 } else { 0.0 },
+                    datum_shift: None,
+                    name: format!(
+                        "UTM {}{}",
+                        zone,
+                        utm_band(latitude).unwrap_or(if latitude < 0.0 { 'S' } else { 'N' })
+                    ),
+                }
+            }
+            Projection::Etrs89Utm32 => GridSystem {
+                ellipsoid: GRS80_ELLIPSOID,
+                central_meridian: 9.0f64.to_radians(),
+                scale: 0.9996,
+                false_easting: 500_000.0,
+                false_northing: 0.0,
+                datum_shift: None,
+                name: "ETRS89 / UTM 32N".to_string(),
+            },
+            Projection::GaussKrueger => {
+                let zone = (longitude / 3.0).round().clamp(2.0, 5.0);
+                GridSystem {
+                    ellipsoid: BESSEL_ELLIPSOID,
+                    central_meridian: (zone * 3.0).to_radians(),
+                    scale: 1.0,
+                    false_easting: zone * 1_000_000.0 + 500_000.0,
+                    false_northing: 0.0,
+                    datum_shift: Some(WGS84_TO_DHDN),
+                    name: format!("DHDN / GK {}", zone),
+                }
+            }
+        }
+    }
+
+    pub fn grid_at(&self, point: &GeoPoint) -> GridSystem {
+        self.grid(point.latitude, point.longitude)
+    }
+
+    pub fn grid_for(&self, pos: WGS84<f32>) -> GridSystem {
+        self.grid(
+            pos.latitude_degrees() as f64,
+            pos.longitude_degrees() as f64,
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectedPoint {
+    pub easting: f64,
+    pub northing: f64,
+}
+
+impl ProjectedPoint {
+    pub const fn new(easting: f64, northing: f64) -> Self {
+        ProjectedPoint { easting, northing }
+    }
+
+    pub fn distance(&self, other: &ProjectedPoint) -> f64 {
+        (self.easting - other.easting).hypot(self.northing - other.northing)
+    }
+}
+
+/// WGS84 latitude/longitude in degrees.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl From<WGS84<f32>> for GeoPoint {
+    fn from(pos: WGS84<f32>) -> Self {
+        GeoPoint {
+            latitude: pos.latitude_degrees() as f64,
+            longitude: pos.longitude_degrees() as f64,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ellipsoid {
+    semi_major_axis: f64,
+    flattening: f64,
+}
+
+const WGS84_ELLIPSOID: Ellipsoid = Ellipsoid {
+    semi_major_axis: 6_378_137.0,
+    flattening: 1.0 / 298.257_223_563,
+};
+
+const GRS80_ELLIPSOID: Ellipsoid = Ellipsoid {
+    semi_major_axis: 6_378_137.0,
+    flattening: 1.0 / 298.257_222_101,
+};
+
+const BESSEL_ELLIPSOID: Ellipsoid = Ellipsoid {
+    semi_major_axis: 6_377_397.155,
+    flattening: 1.0 / 299.152_812_8,
+};
+
+/// Seven parameter Helmert transformation (position vector convention).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Helmert {
+    translation: [f64; 3],
+    /// rotation in arc seconds
+    rotation: [f64; 3],
+    /// scale in ppm
+    scale: f64,
+}
+
+/// Inverse of the DHDN → WGS84 parameter set used for Germany (EPSG:1777).
+const WGS84_TO_DHDN: Helmert = Helmert {
+    translation: [-598.1, -73.7, -418.2],
+    rotation: [-0.202, -0.045, 2.455],
+    scale: -6.7,
+};
+
+impl Helmert {
+    fn apply(&self, [x, y, z]: [f64; 3]) -> [f64; 3] {
+        let [rx, ry, rz] = self.rotation.map(|r| (r / 3600.0).to_radians());
+        let s = 1.0 + self.scale * 1e-6;
+        [
+            self.translation[0] + s * (x - rz * y + ry * z),
+            self.translation[1] + s * (rz * x + y - rx * z),
+            self.translation[2] + s * (-ry * x + rx * y + z),
+        ]
+    }
+
+    fn inverse(&self) -> Helmert {
+        Helmert {
+            translation: self.translation.map(|t| -t),
+            rotation: self.rotation.map(|r| -r),
+            scale: -self.scale,
+        }
+    }
+}
+
+impl Ellipsoid {
+    fn eccentricity_squared(&self) -> f64 {
+        self.flattening * (2.0 - self.flattening)
+    }
+
+    fn to_cartesian(self, latitude: f64, longitude: f64) -> [f64; 3] {
+        let e2 = self.eccentricity_squared();
+        let n = self.semi_major_axis / (1.0 - e2 * latitude.sin().powi(2)).sqrt();
+        [
+            n * latitude.cos() * longitude.cos(),
+            n * latitude.cos() * longitude.sin(),
+            n * (1.0 - e2) * latitude.sin(),
+        ]
+    }
+
+    fn to_geodetic(self, [x, y, z]: [f64; 3]) -> (f64, f64) {
+        let e2 = self.eccentricity_squared();
+        let p = x.hypot(y);
+        let mut latitude = z.atan2(p * (1.0 - e2));
+        for _ in 0..5 {
+            let n = self.semi_major_axis / (1.0 - e2 * latitude.sin().powi(2)).sqrt();
+            latitude = (z + e2 * n * latitude.sin()).atan2(p);
+        }
+        (latitude, y.atan2(x))
+    }
+}
+
+/// A concrete transverse Mercator grid (one zone of a [`Projection`]).
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridSystem {
+    ellipsoid: Ellipsoid,
+    central_meridian: f64,
+    scale: f64,
+    false_easting: f64,
+    false_northing: f64,
+    datum_shift: Option<Helmert>,
+    pub name: String,
+}
+
+struct KruegerSeries {
+    rectifying_radius: f64,
+    eccentricity: f64,
+    alpha: [f64; 4],
+    beta: [f64; 4],
+    delta: [f64; 4],
+}
+
+impl KruegerSeries {
+    /// Tangent of the conformal latitude and the coordinates on the transverse Mercator
+    /// projection of the conformal sphere, for a latitude and longitude from the central
+    /// meridian in radians.
+    fn conformal(&self, latitude: f64, d_lon: f64) -> (f64, f64, f64) {
+        let e = self.eccentricity;
+        let t = (latitude.sin().atanh() - e * (e * latitude.sin()).atanh()).sinh();
+        let xi_prime = t.atan2(d_lon.cos());
+        let eta_prime = (d_lon.sin() / (1.0 + t * t).sqrt()).atanh();
+        (t, xi_prime, eta_prime)
+    }
+}
+
+impl GridSystem {
+    fn series(&self) -> KruegerSeries {
+        let f = self.ellipsoid.flattening;
+        let n = f / (2.0 - f);
+        let (n2, n3, n4) = (n * n, n * n * n, n * n * n * n);
+        KruegerSeries {
+            rectifying_radius: self.ellipsoid.semi_major_axis / (1.0 + n)
+                * (1.0 + n2 / 4.0 + n4 / 64.0),
+            eccentricity: 2.0 * n.sqrt() / (1.0 + n),
+            alpha: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0 + 41.0 * n4 / 180.0,
+                13.0 * n2 / 48.0 - 3.0 * n3 / 5.0 + 557.0 * n4 / 1440.0,
+                61.0 * n3 / 240.0 - 103.0 * n4 / 140.0,
+                49561.0 * n4 / 161280.0,
+            ],
+            beta: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 37.0 * n3 / 96.0 - n4 / 360.0,
+                n2 / 48.0 + n3 / 15.0 - 437.0 * n4 / 1440.0,
+                17.0 * n3 / 480.0 - 37.0 * n4 / 840.0,
+                4397.0 * n4 / 161280.0,
+            ],
+            delta: [
+                2.0 * n - 2.0 * n2 / 3.0 - 2.0 * n3 + 116.0 * n4 / 45.0,
+                7.0 * n2 / 3.0 - 8.0 * n3 / 5.0 - 227.0 * n4 / 45.0,
+                56.0 * n3 / 15.0 - 136.0 * n4 / 35.0,
+                4279.0 * n4 / 630.0,
+            ],
+        }
+    }
+
+    /// WGS84 latitude/longitude in degrees to grid coordinates.
+    pub fn project(&self, latitude: f64, longitude: f64) -> ProjectedPoint {
+        let (mut latitude, mut longitude) = (latitude.to_radians(), longitude.to_radians());
+        if let Some(shift) = self.datum_shift {
+            let cartesian = WGS84_ELLIPSOID.to_cartesian(latitude, longitude);
+            (latitude, longitude) = self.ellipsoid.to_geodetic(shift.apply(cartesian));
+        }
+
+        let series = self.series();
+        let (_, xi_prime, eta_prime) =
+            series.conformal(latitude, longitude - self.central_meridian);
+
+        let (mut xi, mut eta) = (xi_prime, eta_prime);
+        for (j, alpha) in series.alpha.iter().enumerate() {
+            let k = 2.0 * (j + 1) as f64;
+            xi += alpha * (k * xi_prime).sin() * (k * eta_prime).cosh();
+            eta += alpha * (k * xi_prime).cos() * (k * eta_prime).sinh();
+        }
+
+        let k0a = self.scale * series.rectifying_radius;
+        ProjectedPoint {
+            easting: self.false_easting + k0a * eta,
+            northing: self.false_northing + k0a * xi,
+        }
+    }
+
+    pub fn project_wgs(&self, pos: WGS84<f32>) -> ProjectedPoint {
+        self.project(
+            pos.latitude_degrees() as f64,
+            pos.longitude_degrees() as f64,
+        )
+    }
+
+    pub fn project_point(&self, point: &GeoPoint) -> ProjectedPoint {
+        self.project(point.latitude, point.longitude)
+    }
+
+    /// Grid coordinates back to WGS84 latitude/longitude in degrees.
+    pub fn unproject(&self, point: ProjectedPoint) -> (f64, f64) {
+        let (mut latitude, mut longitude) = self.unproject_on_ellipsoid(point);
+        if let Some(shift) = self.datum_shift {
+            let cartesian = self.ellipsoid.to_cartesian(latitude, longitude);
+            (latitude, longitude) = WGS84_ELLIPSOID.to_geodetic(shift.inverse().apply(cartesian));
+        }
+        (latitude.to_degrees(), longitude.to_degrees())
+    }
+
+    /// Grid coordinates to latitude/longitude in radians on the grid's own ellipsoid.
+    fn unproject_on_ellipsoid(&self, point: ProjectedPoint) -> (f64, f64) {
+        let series = self.series();
+        let k0a = self.scale * series.rectifying_radius;
+        let xi = (point.northing - self.false_northing) / k0a;
+        let eta = (point.easting - self.false_easting) / k0a;
+
+        let (mut xi_prime, mut eta_prime) = (xi, eta);
+        for (j, beta) in series.beta.iter().enumerate() {
+            let k = 2.0 * (j + 1) as f64;
+            xi_prime -= beta * (k * xi).sin() * (k * eta).cosh();
+            eta_prime -= beta * (k * xi).cos() * (k * eta).sinh();
+        }
+
+        let chi = (xi_prime.sin() / eta_prime.cosh()).asin();
+        let mut latitude = chi;
+        for (j, delta) in series.delta.iter().enumerate() {
+            latitude += delta * (2.0 * (j + 1) as f64 * chi).sin();
+        }
+        let longitude = self.central_meridian + eta_prime.sinh().atan2(xi_prime.cos());
+        (latitude, longitude)
+    }
+
+    /// Point scale factor of the grid at the given point.
+    ///
+    /// Lengths measured in grid coordinates have to be divided by it (areas by
+    /// its square) to get ground values.
+    pub fn scale_factor(&self, point: ProjectedPoint) -> f64 {
+        let (latitude, longitude) = self.unproject_on_ellipsoid(point);
+        let d_lon = longitude - self.central_meridian;
+        let series = self.series();
+        let (t, xi_prime, eta_prime) = series.conformal(latitude, d_lon);
+
+        // derivative of the Krüger series, after Karney (2011), eq. 25
+        let (mut p, mut q) = (1.0, 0.0);
+        for (j, alpha) in series.alpha.iter().enumerate() {
+            let k = 2.0 * (j + 1) as f64;
+            p += k * alpha * (k * xi_prime).cos() * (k * eta_prime).cosh();
+            q += k * alpha * (k * xi_prime).sin() * (k * eta_prime).sinh();
+        }
+        let e = series.eccentricity;
+        let sin = latitude.sin();
+        self.scale * series.rectifying_radius / self.ellipsoid.semi_major_axis
+            * (1.0 - e * e * sin * sin).sqrt()
+            / latitude.cos()
+            * ((p * p + q * q) / (t * t + d_lon.cos().powi(2))).sqrt()
+    }
+}
+
+fn utm_zone(latitude: f64, longitude: f64) -> u8 {
+    let zone = (((longitude + 180.0) / 6.0).floor() as i32).rem_euclid(60) as u8 + 1;
+    // Norway and Svalbard exceptions
+    if (56.0..64.0).contains(&latitude) && (3.0..12.0).contains(&longitude) {
+        return 32;
+    }
+    if (72.0..84.0).contains(&latitude) && (0.0..42.0).contains(&longitude) {
+        return match longitude {
+            l if l < 9.0 => 31,
+            l if l < 21.0 => 33,
+            l if l < 33.0 => 35,
+            _ => 37,
+        };
+    }
+    zone
+}
+
+fn utm_band(latitude: f64) -> Option<char> {
+    const BANDS: &[u8] = b"CDEFGHJKLMNPQRSTUVWXX";
+    if !(-80.0..=84.0).contains(&latitude) {
+        return None;
+    }
+    let index = (((latitude + 80.0) / 8.0).floor() as usize).min(BANDS.len() - 1);
+    Some(BANDS[index] as char)
+}
+
+/// Area of a simple polygon in grid units (m²), independent of orientation.
+pub fn polygon_area(points: &[ProjectedPoint]) -> f64 {
+    if points.len() < 3 {
+        return 0.0;
+    }
+    let mut sum = 0.0;
+    for (i, p) in points.iter().enumerate() {
+        let q = &points[(i + 1) % points.len()];
+        sum += p.easting * q.northing - q.easting * p.northing;
+    }
+    (sum / 2.0).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::assert_close;
+
+    /// Worked example of EPSG Guidance Note 7-2 (British National Grid), the
+    /// northing at the latitude of origin taken off by projecting the origin.
+    #[test]
+    fn british_national_grid_example() {
+        let grid = |false_northing| GridSystem {
+            ellipsoid: Ellipsoid {
+                semi_major_axis: 6_377_563.396,
+                flattening: 1.0 / 299.324_964_6,
+            },
+            central_meridian: (-2.0f64).to_radians(),
+            scale: 0.999_601_271_7,
+            false_easting: 400_000.0,
+            false_northing,
+            datum_shift: None,
+            name: "OSGB 1936 / British National Grid".to_string(),
+        };
+        let origin = grid(0.0).project(49.0, -2.0).northing;
+        let point = grid(-100_000.0 - origin).project(50.5, 0.5);
+        assert_close(point.easting, 577_274.99, 0.01);
+        assert_close(point.northing, 69_740.50, 0.01);
+    }
+
+    #[test]
+    fn utm_coordinates() {
+        let grid = Projection::Utm.grid(52.0, 9.0);
+        assert_eq!(grid.name, "UTM 32U");
+        let point = grid.project(52.0, 9.0);
+        assert_close(point.easting, 500_000.0, 1e-6);
+        assert_close(point.northing, 5_761_038.213, 0.001);
+        // a zone border, 3° off the central meridian
+        let point = grid.project(52.0, 12.0);
+        assert_close(point.easting, 705_928.919, 0.001);
+        assert_close(point.northing, 5_765_288.255, 0.001);
+    }
+
+    #[test]
+    fn utm_southern_hemisphere() {
+        let grid = Projection::Utm.grid(-33.9, 18.4);
+        assert_eq!(grid.name, "UTM 34H");
+        let point = grid.project(-33.9, 21.0);
+        assert_close(point.easting, 500_000.0, 1e-6);
+        assert!(point.northing > 6_000_000.0 && point.northing < 10_000_000.0);
+    }
+
+    #[test]
+    fn utm_zone_exceptions() {
+        assert_eq!(utm_zone(52.0, 5.0), 31);
+        assert_eq!(utm_zone(60.0, 5.0), 32);
+        assert_eq!(utm_zone(78.0, 8.0), 31);
+        assert_eq!(utm_zone(78.0, 15.0), 33);
+        assert_eq!(utm_zone(78.0, 40.0), 37);
+        assert_eq!(utm_zone(0.0, -180.0), 1);
+        assert_eq!(utm_zone(0.0, 180.0), 1);
+        assert_eq!(utm_band(84.5), None);
+        assert_eq!(utm_band(83.0), Some('X'));
+        assert_eq!(utm_band(-80.0), Some('C'));
+    }
+
+    #[test]
+    fn round_trips() {
+        let points = [(52.0, 9.0), (48.5, 11.3), (54.3, 6.2), (47.4, 13.4)];
+        for projection in Projection::all() {
+            // the approximate inverse of the datum shift costs about a centimetre
+            let tolerance = match projection {
+                Projection::GaussKrueger => 2e-7,
+                _ => 1e-9,
+            };
+            for (latitude, longitude) in points {
+                let grid = projection.grid(latitude, longitude);
+                let back = grid.unproject(grid.project(latitude, longitude));
+                assert_close(back.0, latitude, tolerance);
+                assert_close(back.1, longitude, tolerance);
+            }
+        }
+    }
+
+    #[test]
+    fn etrs89_matches_wgs84_utm() {
+        let etrs = Projection::Etrs89Utm32.grid(48.5, 11.3).project(48.5, 11.3);
+        let wgs = Projection::Utm.grid(48.5, 11.3).project(48.5, 11.3);
+        assert_close(etrs.easting, 669_899.742, 0.001);
+        assert_close(etrs.northing, 5_374_430.243, 0.001);
+        assert!(etrs.distance(&wgs) < 0.001);
+    }
+
+    #[test]
+    fn gauss_krueger_zones() {
+        let grid = Projection::GaussKrueger.grid(52.0, 9.2);
+        assert_eq!(grid.name, "DHDN / GK 3");
+        let point = grid.project(52.0, 9.0);
+        let bessel = GridSystem {
+            datum_shift: None,
+            ..grid.clone()
+        };
+        assert_eq!(bessel.project(52.0, 9.0).easting, 3_500_000.0);
+        // DHDN is off from WGS84 by between 100 and 200 m across Germany
+        let shift = point.distance(&bessel.project(52.0, 9.0));
+        assert!((100.0..250.0).contains(&shift), "shift of {} m", shift);
+        assert_eq!(
+            Projection::GaussKrueger.grid(48.0, 13.4).name,
+            "DHDN / GK 4"
+        );
+        assert_eq!(Projection::GaussKrueger.grid(48.0, 1.0).name, "DHDN / GK 2");
+    }
+
+    /// The negated parameters are an inverse to first order, a few mm off.
+    #[test]
+    fn helmert_inverse() {
+        let cartesian = WGS84_ELLIPSOID.to_cartesian(52f64.to_radians(), 9f64.to_radians());
+        let back = WGS84_TO_DHDN
+            .inverse()
+            .apply(WGS84_TO_DHDN.apply(cartesian));
+        for (back, cartesian) in back.iter().zip(cartesian) {
+            assert_close(*back, cartesian, 0.01);
+        }
+    }
+
+    #[test]
+    fn scale_factor() {
+        let grid = Projection::Utm.grid(52.0, 9.0);
+        assert_close(grid.scale_factor(grid.project(52.0, 9.0)), 0.9996, 1e-12);
+        assert!(grid.scale_factor(grid.project(52.0, 12.0)) > 1.0);
+
+        // grid distance over ground distance of short steps east and north
+        let wgs84 = WGS84_ELLIPSOID;
+        for (latitude, longitude) in [(52.0, 12.0), (47.5, 6.0), (54.9, 10.5)] {
+            let (phi, step) = (f64::to_radians(latitude), 1e-4);
+            let w = (1.0 - wgs84.eccentricity_squared() * phi.sin().powi(2)).sqrt();
+            let prime_vertical = wgs84.semi_major_axis / w;
+            let meridian = prime_vertical * (1.0 - wgs84.eccentricity_squared()) / (w * w);
+            let center = grid.project(latitude, longitude);
+            let east = grid
+                .project(latitude, longitude + step / 2.0)
+                .distance(&grid.project(latitude, longitude - step / 2.0));
+            let north = grid
+                .project(latitude + step / 2.0, longitude)
+                .distance(&grid.project(latitude - step / 2.0, longitude));
+            let scale = grid.scale_factor(center);
+            assert_close(east / (prime_vertical * phi.cos() * step.to_radians()), scale, 1e-9);
+            assert_close(north / (meridian * step.to_radians()), scale, 1e-9);
+        }
+    }
+
+    #[test]
+    fn square_area() {
+        let square = [
+            ProjectedPoint::new(0.0, 0.0),
+            ProjectedPoint::new(10.0, 0.0),
+            ProjectedPoint::new(10.0, 10.0),
+            ProjectedPoint::new(0.0, 10.0),
+        ];
+        assert_eq!(polygon_area(&square), 100.0);
+        let reversed: Vec<_> = square.iter().rev().copied().collect();
+        assert_eq!(polygon_area(&reversed), 100.0);
+        assert_eq!(polygon_area(&square[..2]), 0.0);
+    }
+}
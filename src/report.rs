@@ -0,0 +1,390 @@
+use geo::MultiPolygon;
+use rust_i18n::t;
+
+use crate::fields::{format_duration, format_time, Field, Job, Site};
+use crate::geometry::{from_geo_polygons, PassAnalysis};
+use crate::projection::{GeoPoint, GridSystem, ProjectedPoint, Projection};
+use crate::session::{Session, TrackPoint};
+use crate::units::{Unit, UnitSystem};
+
+/// Steps between two track points longer than this in seconds count as standing still.
+const IDLE_AFTER: f64 = 30.0;
+/// Free space around the thumbnail drawing in metres.
+const THUMBNAIL_MARGIN: f64 = 2.0;
+/// Longer side of the map in job reports in pixels.
+const JOB_MAP_SIZE: f64 = 600.0;
+
+/// Key figures of a recorded session.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionSummary {
+    /// unix times in milliseconds
+    pub started: i64,
+    pub ended: i64,
+    /// covered area in m², overlaps counted once
+    pub area: f64,
+    /// metres driven in total and with the deck engaged
+    pub distance: f64,
+    pub mowed_distance: f64,
+    /// seconds moving with the deck engaged, moving with it off and standing
+    pub engaged_time: f64,
+    pub transit_time: f64,
+    pub idle_time: f64,
+    pub overlap_percentage: Option<f64>,
+    /// cut width in metres
+    pub width: f64,
+}
+
+impl SessionSummary {
+    /// Times and distances come from the track, areas from `analysis` if there is one.
+    pub fn new(session: &Session, analysis: Option<&PassAnalysis>, width: f64) -> Self {
+        let (mut engaged_time, mut transit_time, mut idle_time) = (0.0, 0.0, 0.0);
+        for pair in session.points.windows(2).filter(|pair| !pair[1].after_gap) {
+            let seconds = (pair[1].time - pair[0].time) as f64 / 1000.0;
+            if seconds > IDLE_AFTER {
+                idle_time += seconds;
+            } else if pair[0].engaged && pair[1].engaged {
+                engaged_time += seconds;
+            } else {
+                transit_time += seconds;
+            }
+        }
+        SessionSummary {
+            started: session.points.first().map_or(session.started, |point| point.time),
+            ended: session.last_point().map_or(session.started, |point| point.time),
+            area: analysis.map_or(0.0, PassAnalysis::covered_area),
+            distance: session.distance(),
+            mowed_distance: session.mowed_distance(),
+            engaged_time,
+            transit_time,
+            idle_time,
+            overlap_percentage: analysis.map(PassAnalysis::overlap_percentage),
+            width,
+        }
+    }
+
+    /// Seconds recorded, without the time the app was in the background.
+    pub fn working_time(&self) -> f64 {
+        self.engaged_time + self.transit_time + self.idle_time
+    }
+
+    /// Metres per second while moving.
+    pub fn average_speed(&self) -> f64 {
+        let moving = self.engaged_time + self.transit_time;
+        if moving > 0.0 {
+            self.distance / moving
+        } else {
+            0.0
+        }
+    }
+
+    /// Square metres per hour of working time.
+    pub fn productivity(&self) -> f64 {
+        let hours = self.working_time() / 3600.0;
+        if hours > 0.0 {
+            self.area / hours
+        } else {
+            0.0
+        }
+    }
+
+    /// Covered area compared to the cut width times the mowed distance in percent,
+    /// overlaps and turns lower it.
+    pub fn efficiency(&self) -> Option<f64> {
+        let swept = self.width * self.mowed_distance;
+        (swept > 0.0 && self.area > 0.0).then(|| self.area / swept * 100.0)
+    }
+
+    /// Labels and formatted values, the same in the app and in printed reports.
+    pub fn rows(&self, system: UnitSystem) -> Vec<(String, String)> {
+        let length = Unit::Metre.in_system(system);
+        let width = Unit::Centimetre.in_system(system);
+        let speed = Unit::KilometrePerHour.in_system(system);
+        let percent = |value: Option<f64>| value.map_or_else(|| String::from("-"), |value| format!("{:.1} %", value));
+        vec![
+            (t!("report_time"), format!("{} – {}", format_time(self.started), format_time(self.ended))),
+            (t!("mowed_area"), system.format_area(self.area)),
+            (t!("distance_mowed"), format!("{:.0} {}", length.convert(self.mowed_distance), length.symbol())),
+            (t!("report_distance_total"), format!("{:.0} {}", length.convert(self.distance), length.symbol())),
+            (t!("report_engaged_time"), format_duration(self.engaged_time)),
+            (t!("report_transit_time"), format_duration(self.transit_time)),
+            (t!("report_idle_time"), format_duration(self.idle_time)),
+            (t!("report_average_speed"), format!("{:.1} {}", speed.convert(self.average_speed()), speed.symbol())),
+            (t!("report_productivity"), format!("{}/h", system.format_area(self.productivity()))),
+            (t!("overlap"), percent(self.overlap_percentage)),
+            (t!("report_efficiency"), percent(self.efficiency())),
+            (t!("mower_width"), format!("{:.0} {}", width.convert(self.width), width.symbol())),
+        ]
+    }
+}
+
+/// SVG drawing of `coverage`, `track` and the field `boundary`, north up and `size`
+/// pixels on the longer side.
+///
+/// Empty if there is nothing to draw.
+pub fn thumbnail_svg(
+    grid: &GridSystem,
+    coverage: &MultiPolygon<f64>,
+    track: &[TrackPoint],
+    boundary: &[GeoPoint],
+    size: f64,
+) -> String {
+    let track_points: Vec<(ProjectedPoint, bool)> = track
+        .iter()
+        .map(|point| (grid.project(point.latitude, point.longitude), point.after_gap))
+        .collect();
+    let boundary: Vec<ProjectedPoint> = boundary.iter().map(|point| grid.project_point(point)).collect();
+    let corners = coverage
+        .iter()
+        .flat_map(|polygon| polygon.exterior().coords())
+        .map(|coord| (coord.x, coord.y))
+        .chain(
+            track_points
+                .iter()
+                .map(|(point, _)| point)
+                .chain(&boundary)
+                .map(|point| (point.easting, point.northing)),
+        );
+    let (mut west, mut south, mut east, mut north) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    for (easting, northing) in corners {
+        west = west.min(easting);
+        east = east.max(easting);
+        south = south.min(northing);
+        north = north.max(northing);
+    }
+    if west > east {
+        return String::new();
+    }
+    let (west, south) = (west - THUMBNAIL_MARGIN, south - THUMBNAIL_MARGIN);
+    let (east, north) = (east + THUMBNAIL_MARGIN, north + THUMBNAIL_MARGIN);
+    let scale = size / (east - west).max(north - south);
+    // svg rows count from the top
+    let xy = |easting: f64, northing: f64| format!("{:.1} {:.1}", (easting - west) * scale, (north - northing) * scale);
+
+    let mut area = String::new();
+    for polygon in coverage {
+        for ring in std::iter::once(polygon.exterior()).chain(polygon.interiors()) {
+            for (index, coord) in ring.coords().enumerate() {
+                area.push_str(if index == 0 { "M" } else { "L" });
+                area.push_str(&xy(coord.x, coord.y));
+            }
+            area.push('Z');
+        }
+    }
+    let mut line = String::new();
+    for (index, (point, after_gap)) in track_points.iter().enumerate() {
+        // the track is not drawn across the time the app was in the background
+        line.push_str(if index == 0 || *after_gap { "M" } else { "L" });
+        line.push_str(&xy(point.easting, point.northing));
+    }
+    let mut outline = String::new();
+    for (index, point) in boundary.iter().enumerate() {
+        outline.push_str(if index == 0 { "M" } else { "L" });
+        outline.push_str(&xy(point.easting, point.northing));
+    }
+    if !outline.is_empty() {
+        outline.push('Z');
+    }
+
+    format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.0}" height="{h:.0}" viewBox="0 0 {w:.1} {h:.1}">"#,
+            r##"<path d="{area}" fill="#7cb342" fill-opacity="0.7" fill-rule="evenodd"/>"##,
+            r##"<path d="{line}" fill="none" stroke="#0055a4" stroke-width="1"/>"##,
+            r##"<path d="{outline}" fill="none" stroke="#333" stroke-width="1.5" stroke-dasharray="4 3"/>"##,
+            "</svg>"
+        ),
+        w = (east - west) * scale,
+        h = (north - south) * scale,
+        area = area,
+        line = line,
+        outline = outline,
+    )
+}
+
+/// Text with the characters HTML gives a meaning replaced.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Self-contained HTML page of a report, with a print button that is not printed.
+///
+/// `thumbnail` is inserted as markup, everything else is escaped.
+pub fn report_html(title: &str, rows: &[(String, String)], thumbnail: &str) -> String {
+    let rows: String = rows
+        .iter()
+        .map(|(lable, value)| format!("<tr><th>{}</th><td>{}</td></tr>", escape_html(lable), escape_html(value)))
+        .collect();
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; margin-bottom: 1em; }}
+th, td {{ text-align: left; padding: 0.2em 1em 0.2em 0; border-bottom: 1px solid #ddd; }}
+svg {{ max-width: 100%; height: auto; border: 1px solid #ddd; }}
+@media print {{ .no-print {{ display: none; }} }}
+</style>
+</head>
+<body>
+<button class="no-print" onclick="print()">{print}</button>
+<h1>{title}</h1>
+<table>{rows}</table>
+{thumbnail}
+</body>
+</html>
+"#,
+        lang = rust_i18n::locale(),
+        title = escape_html(title),
+        print = escape_html(&t!("report_print")),
+        rows = rows,
+        thumbnail = thumbnail,
+    )
+}
+
+/// Proof of work for the customer, a self-contained HTML page of one job.
+pub fn job_report_html(
+    job: &Job,
+    field: Option<&Field>,
+    site: Option<&Site>,
+    projection: Projection,
+    gap_threshold: f64,
+    system: UnitSystem,
+) -> String {
+    let session = &job.session;
+    let analysis = session.analyse(projection, gap_threshold);
+    let width = session
+        .profile
+        .as_ref()
+        .map(|profile| profile.width)
+        .or_else(|| session.points.first().map(|point| point.width))
+        .unwrap_or(0.0);
+    let field_name = field.map_or_else(String::new, |field| field.name.clone());
+
+    let mut rows = vec![
+        (t!("site"), site.map_or_else(String::new, |site| site.name.clone())),
+        (t!("field"), field_name.clone()),
+        (t!("report_date"), format_time(job.finished())),
+        (t!("operator"), job.operator.clone()),
+        (t!("mower_profile"), session.profile.as_ref().map_or_else(String::new, |profile| profile.name.clone())),
+    ];
+    // the area stored with the job, so the report agrees with the history whatever the
+    // settings are now; only the overlap comes from analysing the track again
+    let mut summary = SessionSummary::new(session, analysis.as_ref(), width);
+    summary.area = job.area;
+    rows.extend(summary.rows(system));
+
+    let grid = job
+        .coverage
+        .iter()
+        .flatten()
+        .flatten()
+        .next()
+        .map(|point| projection.grid_at(point))
+        .or_else(|| analysis.map(|analysis| analysis.grid));
+    let map = match grid {
+        Some(grid) => thumbnail_svg(
+            &grid,
+            &from_geo_polygons(&grid, &job.coverage),
+            &session.points,
+            field.map(|field| field.boundary.as_slice()).unwrap_or_default(),
+            JOB_MAP_SIZE,
+        ),
+        None => String::new(),
+    };
+    report_html(&format!("{} – {}", t!("job_report"), field_name), &rows, &map)
+}
+
+/// File name for the report of `job`, from the field name and the date.
+pub fn job_report_file_name(job: &Job, field: Option<&Field>) -> String {
+    let name: String = field
+        .map_or("job", |field| field.name.as_str())
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    let date: String = format_time(job.finished()).chars().take(10).collect();
+    format!("{}-{}.html", name, date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{assert_close, grid, track_point};
+
+    fn timed_point(grid: &GridSystem, north: f64, seconds: i64, engaged: bool) -> TrackPoint {
+        TrackPoint {
+            time: 1_700_000_000_000 + seconds * 1000,
+            ..track_point(grid, 0.0, north, engaged)
+        }
+    }
+
+    /// 10 s mowing 10 m, 10 s transit over 10 m, a minute standing while moving 5 m and
+    /// a point after the app was in the background.
+    fn session() -> Session {
+        let grid = grid();
+        let mut resumed = timed_point(&grid, 100.0, 200, false);
+        resumed.after_gap = true;
+        Session {
+            started: 1_700_000_000_000,
+            points: vec![
+                timed_point(&grid, 0.0, 0, true),
+                timed_point(&grid, 10.0, 10, true),
+                timed_point(&grid, 20.0, 20, false),
+                timed_point(&grid, 25.0, 80, false),
+                resumed,
+            ],
+            profile: None,
+            gaps: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn times_and_distances() {
+        let summary = SessionSummary::new(&session(), None, 1.0);
+        assert_eq!(summary.started, 1_700_000_000_000);
+        assert_eq!(summary.ended, 1_700_000_200_000);
+        assert_eq!(summary.engaged_time, 10.0);
+        assert_eq!(summary.transit_time, 10.0);
+        assert_eq!(summary.idle_time, 60.0);
+        assert_eq!(summary.working_time(), 80.0);
+        assert_close(summary.distance, 25.0, 0.05);
+        assert_close(summary.mowed_distance, 10.0, 0.02);
+        assert_close(summary.average_speed(), 1.25, 0.01);
+        assert_eq!(summary.area, 0.0);
+        assert_eq!(summary.overlap_percentage, None);
+        assert_eq!(summary.efficiency(), None);
+    }
+
+    #[test]
+    fn areas() {
+        let session = session();
+        let analysis = session.analyse(Projection::Utm, 0.5);
+        let summary = SessionSummary::new(&session, analysis.as_ref(), 1.0);
+        assert_close(summary.area, 10.0, 0.02);
+        assert_eq!(summary.overlap_percentage, Some(0.0));
+        assert_close(summary.productivity(), summary.area * 3600.0 / 80.0, 1e-9);
+        assert_close(summary.efficiency().unwrap(), 100.0, 0.5);
+        assert_eq!(summary.rows(UnitSystem::Metric)[1].1, "10 m²");
+    }
+
+    #[test]
+    fn empty_session() {
+        let session = Session {
+            started: 1_700_000_000_000,
+            ..Session::new()
+        };
+        let summary = SessionSummary::new(&session, None, 1.0);
+        assert_eq!(summary.started, session.started);
+        assert_eq!(summary.ended, session.started);
+        assert_eq!(summary.working_time(), 0.0);
+        assert_eq!(summary.average_speed(), 0.0);
+        assert_eq!(summary.productivity(), 0.0);
+    }
+}
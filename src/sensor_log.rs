@@ -0,0 +1,241 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use chrono::Utc;
+use nav_types::WGS84;
+
+use crate::position::{FusedState, RawValues};
+use crate::projection::{GridSystem, Projection};
+
+/// Oldest samples are dropped beyond this, about 30 minutes of motion events at 60 Hz.
+pub const MAX_SAMPLES: usize = 100_000;
+
+const CSV_HEADER: &str = "time_ms,latitude,longitude,altitude_m,accuracy_m,\
+grid,easting_m,northing_m,\
+fused_latitude,fused_longitude,fused_altitude_m,fused_easting_m,fused_northing_m,\
+velocity_east_m_s,velocity_north_m_s,fused_velocity_east_m_s,fused_velocity_north_m_s,\
+acceleration_north_m_s2,acceleration_east_m_s2,acceleration_vertical_m_s2,\
+orientation_alpha_deg,orientation_beta_deg,orientation_gamma_deg,\
+declination_deg,vibration_m_s2,fix_interval_ms";
+
+/// Raw and fused sensor values at one moment, positions in degrees and metres.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    /// unix time in milliseconds
+    pub time: i64,
+    pub position: Option<(f64, f64, f64)>,
+    pub accuracy: f32,
+    pub fused_position: Option<(f64, f64, f64)>,
+    /// east/north in m/s
+    pub velocity: [f32; 2],
+    pub fused_velocity: Option<[f32; 2]>,
+    /// world frame, as shown in the raw data panel
+    pub acceleration: [f32; 3],
+    /// radians
+    pub orientation: [f32; 3],
+    pub declination: f32,
+    pub vibration: f32,
+    pub fix_interval: Option<f32>,
+}
+
+fn degrees_and_altitude(wgs: WGS84<f32>) -> (f64, f64, f64) {
+    (
+        wgs.latitude_degrees() as f64,
+        wgs.longitude_degrees() as f64,
+        wgs.altitude() as f64,
+    )
+}
+
+impl Sample {
+    pub fn new(raw: &RawValues, fused: &FusedState) -> Self {
+        Sample {
+            time: Utc::now().timestamp_millis(),
+            position: raw.position.map(|pos| degrees_and_altitude(WGS84::from(pos))),
+            accuracy: raw.accuracy,
+            fused_position: fused.position.map(|pos| degrees_and_altitude(WGS84::from(pos))),
+            velocity: [raw.velocity[0], raw.velocity[1]],
+            fused_velocity: fused.velocity.map(|velocity| [velocity[0], velocity[1]]),
+            acceleration: raw.get_acceleration().into(),
+            orientation: raw.orientation.into(),
+            declination: raw.magnetic_declination,
+            vibration: raw.vibration,
+            fix_interval: raw.fix_interval,
+        }
+    }
+}
+
+/// Missing values are left empty, so spreadsheets and pandas read them as blanks.
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(String::new, |value| value.to_string())
+}
+
+/// Easting and northing of an optional position in `grid`.
+fn grid_coordinates(
+    grid: Option<&GridSystem>,
+    position: Option<(f64, f64, f64)>,
+) -> (Option<f64>, Option<f64>) {
+    grid.zip(position)
+        .map(|(grid, (latitude, longitude, _))| grid.project(latitude, longitude))
+        .map_or((None, None), |point| (Some(point.easting), Some(point.northing)))
+}
+
+/// The latest samples, kept while logging is switched on.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SensorLog {
+    samples: VecDeque<Sample>,
+}
+
+impl SensorLog {
+    pub fn push(&mut self, sample: Sample) {
+        if self.samples.len() >= MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// One line per sample with a header, values in SI units and angles in degrees.
+    ///
+    /// Positions are also given in `projection`, in the grid of the first position so
+    /// that all coordinates of the log can be compared.
+    pub fn to_csv(&self, projection: Projection) -> String {
+        let grid = self
+            .samples
+            .iter()
+            .find_map(|sample| sample.position.or(sample.fused_position))
+            .map(|(latitude, longitude, _)| projection.grid(latitude, longitude));
+        let grid_name = optional(grid.as_ref().map(|grid| grid.name.as_str()));
+        let mut csv = String::from(CSV_HEADER);
+        csv.push('\n');
+        for sample in &self.samples {
+            let (latitude, longitude, altitude) = sample.position.unzip3();
+            let (easting, northing) = grid_coordinates(grid.as_ref(), sample.position);
+            let (fused_latitude, fused_longitude, fused_altitude) = sample.fused_position.unzip3();
+            let (fused_easting, fused_northing) = grid_coordinates(grid.as_ref(), sample.fused_position);
+            let [alpha, beta, gamma] = sample.orientation.map(|angle| angle.to_degrees());
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                sample.time,
+                optional(latitude),
+                optional(longitude),
+                optional(altitude),
+                sample.accuracy,
+                grid_name,
+                optional(easting),
+                optional(northing),
+                optional(fused_latitude),
+                optional(fused_longitude),
+                optional(fused_altitude),
+                optional(fused_easting),
+                optional(fused_northing),
+                sample.velocity[0],
+                sample.velocity[1],
+                optional(sample.fused_velocity.map(|velocity| velocity[0])),
+                optional(sample.fused_velocity.map(|velocity| velocity[1])),
+                sample.acceleration[0],
+                sample.acceleration[1],
+                sample.acceleration[2],
+                alpha,
+                beta,
+                gamma,
+                sample.declination.to_degrees(),
+                sample.vibration,
+                optional(sample.fix_interval),
+            );
+        }
+        csv
+    }
+}
+
+/// Splits an optional triple into three options.
+trait Unzip3<A, B, C> {
+    fn unzip3(self) -> (Option<A>, Option<B>, Option<C>);
+}
+
+impl<A, B, C> Unzip3<A, B, C> for Option<(A, B, C)> {
+    fn unzip3(self) -> (Option<A>, Option<B>, Option<C>) {
+        match self {
+            Some((a, b, c)) => (Some(a), Some(b), Some(c)),
+            None => (None, None, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(time: i64) -> Sample {
+        Sample {
+            time,
+            position: Some((52.0, 9.0, 80.5)),
+            accuracy: 3.5,
+            fused_position: None,
+            velocity: [1.0, -0.5],
+            fused_velocity: None,
+            acceleration: [0.0, 0.25, 9.75],
+            orientation: [std::f32::consts::FRAC_PI_2, 0.0, 0.0],
+            declination: 0.0,
+            vibration: 1.5,
+            fix_interval: None,
+        }
+    }
+
+    #[test]
+    fn csv_lines() {
+        let mut log = SensorLog::default();
+        log.push(sample(1000));
+        log.push(Sample {
+            position: Some((52.0, 9.001, 80.5)),
+            ..sample(2000)
+        });
+        let csv = log.to_csv(Projection::Utm);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        let columns = CSV_HEADER.split(',').count();
+        let values: Vec<&str> = lines[1].split(',').collect();
+        assert_eq!(values.len(), columns);
+        assert_eq!(&values[..5], ["1000", "52", "9", "80.5", "3.5"]);
+        let grid = Projection::Utm.grid(52.0, 9.0);
+        assert_eq!(values[5], grid.name);
+        let point = grid.project(52.0, 9.0);
+        assert_eq!(values[6], point.easting.to_string());
+        assert_eq!(values[7], point.northing.to_string());
+        // missing fused values and fix interval are left blank
+        assert_eq!(&values[8..13], ["", "", "", "", ""]);
+        assert_eq!(&values[15..17], ["", ""]);
+        assert_eq!(values[20], "90");
+        assert_eq!(values[columns - 1], "");
+        // later samples are given in the same grid
+        let next: Vec<&str> = lines[2].split(',').collect();
+        assert_eq!(next[5], values[5]);
+        let easting: f64 = next[6].parse().unwrap();
+        assert!((easting - point.easting - 68.6).abs() < 0.1);
+    }
+
+    #[test]
+    fn oldest_samples_are_dropped() {
+        let mut log = SensorLog::default();
+        for time in 0..MAX_SAMPLES as i64 + 2 {
+            log.push(sample(time));
+        }
+        assert_eq!(log.len(), MAX_SAMPLES);
+        assert!(log.to_csv(Projection::Utm).lines().nth(1).unwrap().starts_with("2,"));
+        log.clear();
+        assert!(log.is_empty());
+        assert_eq!(log.to_csv(Projection::Utm), format!("{}\n", CSV_HEADER));
+    }
+}
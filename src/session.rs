@@ -0,0 +1,177 @@
+use chrono::Utc;
+use nav_types::WGS84;
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{LeverArm, PassAnalysis, SwathPoint};
+use crate::profile::MowerProfile;
+use crate::projection::{GridSystem, Projection};
+
+const EARTH_RADIUS: f64 = 6_371_008.8;
+/// Minimum distance between two recorded track points in metres.
+pub const MIN_POINT_DISTANCE: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrackPoint {
+    /// unix time in milliseconds
+    pub time: i64,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// GNSS accuracy (1σ radius) at the time of the fix in metres
+    pub accuracy: f32,
+    /// cut width at this point in metres
+    pub width: f64,
+    /// whether the deck was cutting, transit points do not count as mowed
+    pub engaged: bool,
+    /// direction the vehicle faced in radians clockwise from north, opposite to the
+    /// direction of travel while reversing
+    pub heading: Option<f64>,
+    /// offset of the deck centre from the phone
+    pub lever_arm: LeverArm,
+    /// first point after the app was in the background, the track is broken before it
+    #[serde(default)]
+    pub after_gap: bool,
+}
+
+impl TrackPoint {
+    pub fn new(
+        pos: WGS84<f32>,
+        accuracy: f32,
+        width: f64,
+        engaged: bool,
+        heading: Option<f64>,
+        lever_arm: LeverArm,
+    ) -> Self {
+        TrackPoint {
+            time: Utc::now().timestamp_millis(),
+            latitude: pos.latitude_degrees() as f64,
+            longitude: pos.longitude_degrees() as f64,
+            accuracy,
+            width,
+            engaged,
+            heading,
+            lever_arm,
+            after_gap: false,
+        }
+    }
+
+    /// The deck centre and cut width at this point.
+    ///
+    /// Without a heading the lever arm cannot be applied and the phone position is used.
+    pub fn swath_point(&self, grid: &GridSystem) -> SwathPoint {
+        let antenna = grid.project(self.latitude, self.longitude);
+        let deck = match self.heading {
+            Some(heading) => self.lever_arm.apply(antenna, heading),
+            None => antenna,
+        };
+        SwathPoint::new(deck, self.width)
+    }
+
+    /// Great circle distance in metres.
+    pub fn distance(&self, other: &TrackPoint) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().asin()
+    }
+}
+
+/// Time the app was in the background and did not record, unix times in milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gap {
+    pub start: i64,
+    pub end: i64,
+}
+
+/// One recording of the mower's track, from app start or the last reset.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// unix time in milliseconds
+    pub started: i64,
+    pub points: Vec<TrackPoint>,
+    /// the machine the session was recorded with
+    pub profile: Option<MowerProfile>,
+    #[serde(default)]
+    pub gaps: Vec<Gap>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session {
+            started: Utc::now().timestamp_millis(),
+            points: Vec::new(),
+            profile: None,
+            gaps: Vec::new(),
+        }
+    }
+
+    /// Adds `point` if it is far enough from the last one, returns whether it was added.
+    pub fn record(&mut self, mut point: TrackPoint) -> bool {
+        let far_enough = self
+            .last_point()
+            .map_or(true, |last| last.distance(&point) >= MIN_POINT_DISTANCE);
+        if far_enough {
+            point.after_gap = match (self.last_point(), self.gaps.last()) {
+                (Some(last), Some(gap)) => last.time <= gap.start,
+                _ => false,
+            };
+            self.points.push(point);
+        }
+        far_enough
+    }
+
+    /// Notes that nothing was recorded between `start` and `end`.
+    pub fn mark_gap(&mut self, start: i64, end: i64) {
+        self.gaps.push(Gap { start, end });
+    }
+
+    /// Whether a track is being recorded.
+    pub fn is_recording(&self) -> bool {
+        !self.points.is_empty()
+    }
+
+    pub fn last_point(&self) -> Option<&TrackPoint> {
+        self.points.last()
+    }
+
+    /// Distance driven in metres, the way across gaps is unknown and not counted.
+    pub fn distance(&self) -> f64 {
+        self.points
+            .windows(2)
+            .filter(|pair| !pair[1].after_gap)
+            .map(|pair| pair[0].distance(&pair[1]))
+            .sum()
+    }
+
+    /// Distance driven with the deck engaged in metres.
+    pub fn mowed_distance(&self) -> f64 {
+        self.points
+            .windows(2)
+            .filter(|pair| pair[0].engaged && pair[1].engaged && !pair[1].after_gap)
+            .map(|pair| pair[0].distance(&pair[1]))
+            .sum()
+    }
+
+    /// The grid all geometry of this session is built in, fixed by its first point.
+    pub fn grid(&self, projection: Projection) -> Option<GridSystem> {
+        self.points
+            .first()
+            .map(|point| projection.grid(point.latitude, point.longitude))
+    }
+
+    pub fn analyse(&self, projection: Projection, gap_threshold: f64) -> Option<PassAnalysis> {
+        self.grid(projection)
+            .map(|grid| PassAnalysis::from_track(grid, &self.points, gap_threshold))
+    }
+
+    /// Elapsed time in seconds.
+    pub fn elapsed(&self) -> f64 {
+        (Utc::now().timestamp_millis() - self.started) as f64 / 1000.0
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
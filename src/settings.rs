@@ -0,0 +1,520 @@
+use std::fmt;
+
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sycamore::prelude::*;
+
+use crate::guidance::GuidanceLine;
+use crate::language::Language;
+use crate::profile::MowerProfile;
+use crate::projection::Projection;
+use crate::tiles::{TileSource, MAX_NATIVE_ZOOM, MIN_OFFLINE_ZOOM};
+use crate::units::UnitSystem;
+use crate::utils::{get_stored_text, log_to_browser, remove_stored_item, set_stored_text};
+
+/// Bumped whenever a field is renamed or changes its meaning, see [`MIGRATIONS`].
+pub const SETTINGS_VERSION: u64 = 1;
+const SETTINGS_KEY: &str = "settings";
+/// Settings that could not be read are kept here instead of being overwritten.
+const UNREADABLE_SETTINGS_KEY: &str = "settings_unreadable";
+
+/// Keys every setting was stored under on its own before version 1.
+const LEGACY_KEYS: [&str; 17] = [
+    "mower_with",
+    "deck_offset_forward",
+    "deck_offset_lateral",
+    "mower_profiles",
+    "active_profile",
+    "projection",
+    "guidance_overlap",
+    "gap_threshold",
+    "deck_auto_detect",
+    "vibration_threshold",
+    "guidance_line",
+    "geo_high_accuracy",
+    "geo_maximum_age",
+    "geo_timeout",
+    "active_field",
+    "show_last_mowed",
+    "offline_max_zoom",
+];
+
+/// Migration `n` turns settings of version `n` into version `n + 1`.
+const MIGRATIONS: [fn(&mut Map<String, Value>); SETTINGS_VERSION as usize] = [from_legacy_keys];
+
+#[derive(Debug)]
+pub enum SettingsError {
+    /// not JSON or a field has the wrong type
+    Syntax(serde_json::Error),
+    /// JSON, but not an object
+    NotAnObject,
+    /// written by a newer version of the app
+    TooNew(u64),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Syntax(error) => write!(f, "{}", error),
+            SettingsError::NotAnObject => write!(f, "not a settings object"),
+            SettingsError::TooNew(version) => write!(f, "settings version {} is not supported", version),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u64,
+    pub language: Language,
+    pub profiles: Vec<MowerProfile>,
+    /// name put on job reports
+    pub operator: String,
+    pub active_profile: usize,
+    pub projection: Projection,
+    pub units: UnitSystem,
+    /// metres
+    pub guidance_overlap: f64,
+    /// metres
+    pub gap_threshold: f64,
+    pub deck_auto_detect: bool,
+    /// m/s²
+    pub vibration_threshold: f64,
+    pub guidance_line: Option<GuidanceLine>,
+    pub geo_high_accuracy: bool,
+    /// seconds
+    pub geo_maximum_age: f64,
+    /// seconds, 0 for none
+    pub geo_timeout: f64,
+    pub active_field: Option<i64>,
+    pub show_last_mowed: bool,
+    pub offline_max_zoom: f64,
+    pub tile_source: TileSource,
+    /// template for [`TileSource::Custom`]
+    pub custom_tile_url: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            version: SETTINGS_VERSION,
+            language: Language::Browser,
+            profiles: vec![MowerProfile::new(t!("default_profile"))],
+            operator: String::new(),
+            active_profile: 0,
+            projection: Projection::Utm,
+            units: UnitSystem::Metric,
+            guidance_overlap: 0.1,
+            gap_threshold: 0.5,
+            deck_auto_detect: false,
+            vibration_threshold: 1.0,
+            guidance_line: None,
+            geo_high_accuracy: true,
+            geo_maximum_age: 0.0,
+            geo_timeout: 0.0,
+            active_field: None,
+            show_last_mowed: false,
+            offline_max_zoom: 18.0,
+            tile_source: TileSource::OpenStreetMap,
+            custom_tile_url: String::new(),
+        }
+    }
+}
+
+/// Replaces `value` by `default` unless it is a number of at least 0.
+fn non_negative(value: &mut f64, default: f64) {
+    if !(*value >= 0.0 && value.is_finite()) {
+        *value = default;
+    }
+}
+
+impl Settings {
+    /// Puts values the app cannot work with back to their defaults.
+    pub fn validate(&mut self) {
+        let defaults = Settings::default();
+        self.profiles.iter_mut().for_each(MowerProfile::validate);
+        if self.profiles.is_empty() {
+            self.profiles = defaults.profiles;
+        }
+        if self.active_profile >= self.profiles.len() {
+            self.active_profile = 0;
+        }
+        non_negative(&mut self.guidance_overlap, defaults.guidance_overlap);
+        non_negative(&mut self.gap_threshold, defaults.gap_threshold);
+        non_negative(&mut self.vibration_threshold, defaults.vibration_threshold);
+        non_negative(&mut self.geo_maximum_age, defaults.geo_maximum_age);
+        non_negative(&mut self.geo_timeout, defaults.geo_timeout);
+        non_negative(&mut self.offline_max_zoom, defaults.offline_max_zoom);
+        self.offline_max_zoom = self
+            .offline_max_zoom
+            .round()
+            .clamp(MIN_OFFLINE_ZOOM as f64, MAX_NATIVE_ZOOM as f64);
+    }
+
+    /// Reads settings of any known version, as stored or exported.
+    ///
+    /// Fields that cannot be read keep their defaults.
+    pub fn from_json(json: &str) -> Result<Self, SettingsError> {
+        let value = serde_json::from_str(json).map_err(SettingsError::Syntax)?;
+        Self::from_value(value).map(|(settings, _)| settings)
+    }
+
+    /// The settings and the names of the fields that could not be read.
+    fn from_value(value: Value) -> Result<(Self, Vec<String>), SettingsError> {
+        let Value::Object(mut settings) = value else {
+            return Err(SettingsError::NotAnObject);
+        };
+        let version = settings.get("version").and_then(Value::as_u64).unwrap_or(0);
+        if version > SETTINGS_VERSION {
+            return Err(SettingsError::TooNew(version));
+        }
+        for migration in &MIGRATIONS[version as usize..] {
+            migration(&mut settings);
+        }
+        settings.insert(String::from("version"), Value::from(SETTINGS_VERSION));
+        let (mut settings, unreadable) = match serde_json::from_value(Value::Object(settings.clone())) {
+            Ok(settings) => (settings, Vec::new()),
+            Err(_) => Self::readable_fields(settings),
+        };
+        settings.validate();
+        Ok((settings, unreadable))
+    }
+
+    /// Takes every field of `fields` that can be read on its own, so that one bad
+    /// value does not cost all the others.
+    fn readable_fields(fields: Map<String, Value>) -> (Self, Vec<String>) {
+        let Ok(Value::Object(defaults)) = serde_json::to_value(Settings::default()) else {
+            return (Settings::default(), fields.into_iter().map(|(key, _)| key).collect());
+        };
+        let mut merged = defaults.clone();
+        let mut unreadable = Vec::new();
+        for (key, value) in fields {
+            let mut single = defaults.clone();
+            single.insert(key.clone(), value.clone());
+            if serde_json::from_value::<Settings>(Value::Object(single)).is_ok() {
+                merged.insert(key, value);
+            } else {
+                unreadable.push(key);
+            }
+        }
+        let settings = serde_json::from_value(Value::Object(merged)).unwrap_or_default();
+        (settings, unreadable)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+/// Version 0 are the separate keys, collected into one object by [`legacy_settings`].
+fn from_legacy_keys(settings: &mut Map<String, Value>) {
+    let mut number = |key: &str| settings.remove(key).and_then(|value| value.as_f64());
+    let (width, forward, lateral) = (
+        number("mower_with"),
+        number("deck_offset_forward"),
+        number("deck_offset_lateral"),
+    );
+    let profiles = settings.remove("mower_profiles").unwrap_or_else(|| {
+        // the first profile is made from the settings used before profiles existed
+        let mut profile = MowerProfile::new(t!("default_profile"));
+        profile.width = width.unwrap_or(profile.width);
+        profile.lever_arm.forward = forward.unwrap_or(0.0);
+        profile.lever_arm.lateral = lateral.unwrap_or(0.0);
+        serde_json::to_value(vec![profile]).unwrap_or_default()
+    });
+    settings.insert(String::from("profiles"), profiles);
+}
+
+/// The single keys of version 0 as one object, the keys whose text is not JSON and
+/// the stored texts, to keep them if anything cannot be read.
+fn legacy_settings() -> (Value, Vec<String>, Map<String, Value>) {
+    let mut settings = Map::new();
+    let mut unreadable = Vec::new();
+    let mut texts = Map::new();
+    for key in LEGACY_KEYS {
+        let text = get_stored_text(key, String::new());
+        if text.is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&text) {
+            Ok(value) => {
+                settings.insert(String::from(key), value);
+            }
+            Err(error) => {
+                log_to_browser(format!("Setting {} not readable: {}", key, error));
+                unreadable.push(String::from(key));
+            }
+        }
+        texts.insert(String::from(key), Value::String(text));
+    }
+    (Value::Object(settings), unreadable, texts)
+}
+
+/// The stored settings, migrated from the single keys of older versions if needed.
+///
+/// Settings that cannot be read are logged and keep their defaults. The stored text is
+/// then copied to [`UNREADABLE_SETTINGS_KEY`] before anything is overwritten.
+pub fn load_settings() -> Settings {
+    let text = get_stored_text(SETTINGS_KEY, String::new());
+    let legacy = text.is_empty();
+    let (result, mut unreadable, original) = if legacy {
+        let (value, unreadable, texts) = legacy_settings();
+        let original = if texts.is_empty() {
+            String::new()
+        } else {
+            Value::Object(texts).to_string()
+        };
+        (Settings::from_value(value), unreadable, original)
+    } else {
+        let result = serde_json::from_str(&text)
+            .map_err(SettingsError::Syntax)
+            .and_then(Settings::from_value);
+        (result, Vec::new(), text)
+    };
+    let settings = match result {
+        Ok((settings, fields)) => {
+            unreadable.extend(fields);
+            if !unreadable.is_empty() {
+                log_to_browser(format!(
+                    "Settings {} not readable, using their defaults",
+                    unreadable.join(", ")
+                ));
+            }
+            settings
+        }
+        Err(error) => {
+            log_to_browser(format!("Settings not readable, using defaults: {}", error));
+            unreadable.push(String::from(SETTINGS_KEY));
+            Settings::default()
+        }
+    };
+    if !unreadable.is_empty() && !original.is_empty() {
+        set_stored_text(UNREADABLE_SETTINGS_KEY, original);
+    }
+    if legacy {
+        LEGACY_KEYS.into_iter().for_each(remove_stored_item);
+    }
+    settings
+}
+
+/// One signal per setting, so inputs can bind to them.
+#[derive(Clone, Copy)]
+pub struct SettingsSignals {
+    pub language: Signal<Language>,
+    pub profiles: Signal<Vec<MowerProfile>>,
+    pub operator: Signal<String>,
+    pub active_profile: Signal<usize>,
+    pub projection: Signal<Projection>,
+    pub units: Signal<UnitSystem>,
+    pub guidance_overlap: Signal<f64>,
+    pub gap_threshold: Signal<f64>,
+    pub deck_auto_detect: Signal<bool>,
+    pub vibration_threshold: Signal<f64>,
+    pub guidance_line: Signal<Option<GuidanceLine>>,
+    pub geo_high_accuracy: Signal<bool>,
+    pub geo_maximum_age: Signal<f64>,
+    pub geo_timeout: Signal<f64>,
+    pub active_field: Signal<Option<i64>>,
+    pub show_last_mowed: Signal<bool>,
+    pub offline_max_zoom: Signal<f64>,
+    pub tile_source: Signal<TileSource>,
+    pub custom_tile_url: Signal<String>,
+}
+
+impl SettingsSignals {
+    fn new(settings: Settings) -> Self {
+        SettingsSignals {
+            language: create_signal(settings.language),
+            profiles: create_signal(settings.profiles),
+            operator: create_signal(settings.operator),
+            active_profile: create_signal(settings.active_profile),
+            projection: create_signal(settings.projection),
+            units: create_signal(settings.units),
+            guidance_overlap: create_signal(settings.guidance_overlap),
+            gap_threshold: create_signal(settings.gap_threshold),
+            deck_auto_detect: create_signal(settings.deck_auto_detect),
+            vibration_threshold: create_signal(settings.vibration_threshold),
+            guidance_line: create_signal(settings.guidance_line),
+            geo_high_accuracy: create_signal(settings.geo_high_accuracy),
+            geo_maximum_age: create_signal(settings.geo_maximum_age),
+            geo_timeout: create_signal(settings.geo_timeout),
+            active_field: create_signal(settings.active_field),
+            show_last_mowed: create_signal(settings.show_last_mowed),
+            offline_max_zoom: create_signal(settings.offline_max_zoom),
+            tile_source: create_signal(settings.tile_source),
+            custom_tile_url: create_signal(settings.custom_tile_url),
+        }
+    }
+
+    /// Current values, tracked by the surrounding effect.
+    pub fn get(&self) -> Settings {
+        Settings {
+            version: SETTINGS_VERSION,
+            language: self.language.get(),
+            profiles: self.profiles.get_clone(),
+            operator: self.operator.get_clone(),
+            active_profile: self.active_profile.get(),
+            projection: self.projection.get(),
+            units: self.units.get(),
+            guidance_overlap: self.guidance_overlap.get(),
+            gap_threshold: self.gap_threshold.get(),
+            deck_auto_detect: self.deck_auto_detect.get(),
+            vibration_threshold: self.vibration_threshold.get(),
+            guidance_line: self.guidance_line.get_clone(),
+            geo_high_accuracy: self.geo_high_accuracy.get(),
+            geo_maximum_age: self.geo_maximum_age.get(),
+            geo_timeout: self.geo_timeout.get(),
+            active_field: self.active_field.get(),
+            show_last_mowed: self.show_last_mowed.get(),
+            offline_max_zoom: self.offline_max_zoom.get(),
+            tile_source: self.tile_source.get(),
+            custom_tile_url: self.custom_tile_url.get_clone(),
+        }
+    }
+
+    pub fn set(&self, settings: Settings) {
+        self.language.set(settings.language);
+        self.profiles.set(settings.profiles);
+        self.operator.set(settings.operator);
+        self.active_profile.set(settings.active_profile);
+        self.projection.set(settings.projection);
+        self.units.set(settings.units);
+        self.guidance_overlap.set(settings.guidance_overlap);
+        self.gap_threshold.set(settings.gap_threshold);
+        self.deck_auto_detect.set(settings.deck_auto_detect);
+        self.vibration_threshold.set(settings.vibration_threshold);
+        self.guidance_line.set(settings.guidance_line);
+        self.geo_high_accuracy.set(settings.geo_high_accuracy);
+        self.geo_maximum_age.set(settings.geo_maximum_age);
+        self.geo_timeout.set(settings.geo_timeout);
+        self.active_field.set(settings.active_field);
+        self.show_last_mowed.set(settings.show_last_mowed);
+        self.offline_max_zoom.set(settings.offline_max_zoom);
+        self.tile_source.set(settings.tile_source);
+        self.custom_tile_url.set(settings.custom_tile_url);
+    }
+}
+
+/// Loads the settings and stores them again on every change.
+pub fn create_settings() -> SettingsSignals {
+    let settings = SettingsSignals::new(load_settings());
+    create_effect(move || {
+        let mut stored = settings.get();
+        stored.validate();
+        set_stored_text(SETTINGS_KEY, stored.to_json());
+    });
+    settings
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn legacy_keys() {
+        let legacy = json!({
+            "mower_with": 1.2,
+            "deck_offset_forward": 0.3,
+            "projection": "GaussKrueger",
+            "gap_threshold": 0.4,
+            "show_last_mowed": true,
+        });
+        let (settings, unreadable) = Settings::from_value(legacy).unwrap();
+        assert!(unreadable.is_empty());
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.profiles.len(), 1);
+        assert_eq!(settings.profiles[0].width, 1.2);
+        assert_eq!(settings.profiles[0].lever_arm.forward, 0.3);
+        assert_eq!(settings.profiles[0].lever_arm.lateral, 0.0);
+        assert_eq!(settings.projection, Projection::GaussKrueger);
+        assert_eq!(settings.gap_threshold, 0.4);
+        assert!(settings.show_last_mowed);
+    }
+
+    #[test]
+    fn legacy_profiles_win_over_mower_width() {
+        let mut profile = MowerProfile::new(String::from("Tractor"));
+        profile.width = 2.5;
+        let legacy = json!({
+            "mower_with": 1.2,
+            "mower_profiles": [profile.clone()],
+        });
+        let settings = Settings::from_json(&legacy.to_string()).unwrap();
+        assert_eq!(settings.profiles, vec![profile]);
+        assert!(!settings.to_json().contains("mower_with"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let settings = Settings {
+            operator: String::from("Jo"),
+            language: Language::German,
+            units: UnitSystem::UsSurvey,
+            tile_source: TileSource::Custom,
+            custom_tile_url: String::from("https://tiles.example/{z}/{x}/{y}.png"),
+            ..Settings::default()
+        };
+        assert_eq!(Settings::from_json(&settings.to_json()).unwrap(), settings);
+    }
+
+    #[test]
+    fn unreadable_field_keeps_default() {
+        let stored = json!({
+            "version": SETTINGS_VERSION,
+            "operator": "Jo",
+            "gap_threshold": "wide",
+            "deck_auto_detect": true,
+        });
+        let (settings, unreadable) = Settings::from_value(stored).unwrap();
+        assert_eq!(unreadable, vec![String::from("gap_threshold")]);
+        assert_eq!(settings.gap_threshold, Settings::default().gap_threshold);
+        assert_eq!(settings.operator, "Jo");
+        assert!(settings.deck_auto_detect);
+    }
+
+    #[test]
+    fn unknown_fields_are_ignored() {
+        let settings = Settings::from_json(r#"{"version": 1, "removed_setting": 3}"#).unwrap();
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    fn unreadable_settings() {
+        assert!(matches!(
+            Settings::from_json(r#"{"version": 99}"#),
+            Err(SettingsError::TooNew(99))
+        ));
+        assert!(matches!(
+            Settings::from_json("[1, 2]"),
+            Err(SettingsError::NotAnObject)
+        ));
+        assert!(matches!(
+            Settings::from_json("{"),
+            Err(SettingsError::Syntax(_))
+        ));
+    }
+
+    #[test]
+    fn validate() {
+        let mut settings = Settings {
+            profiles: Vec::new(),
+            active_profile: 3,
+            gap_threshold: -1.0,
+            vibration_threshold: f64::NAN,
+            offline_max_zoom: 25.3,
+            ..Settings::default()
+        };
+        settings.validate();
+        let defaults = Settings::default();
+        assert_eq!(settings.profiles, defaults.profiles);
+        assert_eq!(settings.active_profile, 0);
+        assert_eq!(settings.gap_threshold, defaults.gap_threshold);
+        assert_eq!(settings.vibration_threshold, defaults.vibration_threshold);
+        assert_eq!(settings.offline_max_zoom, MAX_NATIVE_ZOOM as f64);
+    }
+}
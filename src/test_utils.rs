@@ -0,0 +1,49 @@
+//! Helpers shared by the unit tests.
+
+use crate::geometry::{LeverArm, SwathPoint};
+use crate::projection::{GridSystem, ProjectedPoint, Projection};
+use crate::session::TrackPoint;
+
+/// Easting and northing of the point on the central meridian the test tracks start at.
+const ORIGIN: (f64, f64) = (500_000.0, 5_761_000.0);
+
+pub fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{} is not within {} of {}",
+        actual,
+        tolerance,
+        expected
+    );
+}
+
+/// UTM grid of zone 32N, the test tracks lie in northern Germany.
+pub fn grid() -> GridSystem {
+    Projection::Utm.grid(52.0, 9.0)
+}
+
+/// Grid point `east`/`north` metres from the origin of the test tracks.
+pub fn grid_point(east: f64, north: f64) -> ProjectedPoint {
+    ProjectedPoint::new(ORIGIN.0 + east, ORIGIN.1 + north)
+}
+
+/// Swath point `east`/`north` metres from the origin of the test tracks.
+pub fn swath(east: f64, north: f64, width: f64) -> SwathPoint {
+    SwathPoint::new(grid_point(east, north), width)
+}
+
+/// Track point `east`/`north` metres from the origin with a 1 m deck, recorded at 0.
+pub fn track_point(grid: &GridSystem, east: f64, north: f64, engaged: bool) -> TrackPoint {
+    let (latitude, longitude) = grid.unproject(grid_point(east, north));
+    TrackPoint {
+        time: 0,
+        latitude,
+        longitude,
+        accuracy: 1.0,
+        width: 1.0,
+        engaged,
+        heading: None,
+        lever_arm: LeverArm::default(),
+        after_gap: false,
+    }
+}
@@ -0,0 +1,254 @@
+use std::f64::consts::PI;
+
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{window, Cache};
+
+use crate::components::SelectOption;
+
+/// Must match `tileCacheName` in `sw.js`.
+const TILE_CACHE: &str = "tile-cache";
+/// Highest zoom level any tile source renders.
+pub const MAX_NATIVE_ZOOM: u32 = 19;
+/// Lowest zoom level downloaded for offline use, enough to find the site again.
+pub const MIN_OFFLINE_ZOOM: u32 = 12;
+/// Downloads larger than this are refused, to spare the tile server and the phone's storage.
+pub const MAX_TILES: usize = 5000;
+/// Leaflet is told to use only this subdomain, so a tile is cached under the same URL
+/// however Leaflet asks for it.
+pub const SUBDOMAIN: &str = "a";
+
+/// Where the map background comes from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TileSource {
+    /// the OpenStreetMap servers, whose usage policy forbids bulk downloads
+    #[default]
+    OpenStreetMap,
+    /// TopPlusOpen of the German federal mapping agency, open data that may be cached
+    TopPlusOpen,
+    /// a URL template entered by the user, who is responsible for its terms
+    Custom,
+}
+
+/// The tile server of a [`TileSource`], as Leaflet and the download need it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileServer {
+    /// template with `{z}`, `{x}`, `{y}` and optionally `{s}`
+    pub url: String,
+    pub attribution: &'static str,
+    pub max_native_zoom: u32,
+    /// whether the terms allow downloading an area for offline use
+    pub offline: bool,
+}
+
+impl TileSource {
+    /// `custom_url` is only used for [`TileSource::Custom`].
+    pub fn server(&self, custom_url: &str) -> TileServer {
+        match self {
+            TileSource::OpenStreetMap => TileServer {
+                url: String::from("https://tile.openstreetmap.org/{z}/{x}/{y}.png"),
+                attribution: "&copy; <a href=\"https://www.openstreetmap.org/copyright\">OpenStreetMap</a>",
+                max_native_zoom: 19,
+                offline: false,
+            },
+            TileSource::TopPlusOpen => TileServer {
+                url: String::from(
+                    "https://sgx.geodatenzentrum.de/wmts_topplus_open/tile/1.0.0/web/default/WEBMERCATOR/{z}/{y}/{x}.png",
+                ),
+                attribution: "&copy; <a href=\"https://www.bkg.bund.de\">BKG</a>, <a href=\"https://www.govdata.de/dl-de/by-2-0\">dl-de/by-2-0</a>",
+                max_native_zoom: 18,
+                offline: true,
+            },
+            TileSource::Custom => TileServer {
+                url: String::from(custom_url.trim()),
+                attribution: "",
+                max_native_zoom: MAX_NATIVE_ZOOM,
+                offline: true,
+            },
+        }
+    }
+}
+
+impl SelectOption for TileSource {
+    fn all() -> Vec<Self> {
+        vec![TileSource::OpenStreetMap, TileSource::TopPlusOpen, TileSource::Custom]
+    }
+
+    fn lable(&self) -> String {
+        match self {
+            TileSource::OpenStreetMap => t!("tile_source_osm"),
+            TileSource::TopPlusOpen => t!("tile_source_topplus"),
+            TileSource::Custom => t!("tile_source_custom"),
+        }
+    }
+}
+
+/// WGS84 bounding box in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GeoBounds {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+/// Tile column of `longitude`, counting on eastwards past the antimeridian.
+fn tile_x(longitude: f64, zoom: u32) -> u64 {
+    let tiles = 2f64.powi(zoom as i32);
+    ((longitude + 180.0) / 360.0 * tiles).floor().max(0.0) as u64
+}
+
+fn tile_y(latitude: f64, zoom: u32) -> u32 {
+    let tiles = 2f64.powi(zoom as i32);
+    let latitude = latitude.clamp(-85.0511, 85.0511).to_radians();
+    let y = (1.0 - (latitude.tan() + 1.0 / latitude.cos()).ln() / PI) / 2.0 * tiles;
+    y.floor().clamp(0.0, tiles - 1.0) as u32
+}
+
+/// Column and row ranges of the tiles covering `bounds` at `zoom`.
+///
+/// Leaflet reports longitudes beyond ±180° once the map is panned round the world, and
+/// a view across the antimeridian has its east edge west of its west edge. Columns
+/// therefore start in the first world and run on past its last column, they are taken
+/// modulo the number of columns for the tile address.
+fn tile_range(bounds: GeoBounds, zoom: u32) -> (std::ops::RangeInclusive<u64>, std::ops::RangeInclusive<u32>) {
+    let west = (bounds.west + 180.0).rem_euclid(360.0) - 180.0;
+    let width = if bounds.east < bounds.west {
+        bounds.east - bounds.west + 360.0
+    } else {
+        bounds.east - bounds.west
+    };
+    let first = tile_x(west, zoom);
+    let last = tile_x(west + width, zoom).min(first + (1 << zoom) - 1);
+    // tile rows count from the north
+    (first..=last, tile_y(bounds.north, zoom)..=tile_y(bounds.south, zoom))
+}
+
+fn offline_zooms(server: &TileServer, max_zoom: u32) -> std::ops::RangeInclusive<u32> {
+    MIN_OFFLINE_ZOOM..=max_zoom.min(server.max_native_zoom)
+}
+
+/// Number of tiles covering `bounds` from the lowest offline zoom to `max_zoom`.
+///
+/// Counted from the tile ranges, so it stays cheap for a view of the whole world.
+pub fn tile_count(server: &TileServer, bounds: GeoBounds, max_zoom: u32) -> u64 {
+    offline_zooms(server, max_zoom)
+        .map(|zoom| {
+            let (columns, rows) = tile_range(bounds, zoom);
+            (columns.end() + 1 - columns.start()) * (rows.count() as u64)
+        })
+        .sum()
+}
+
+/// URLs of all tiles covering `bounds`, only to be built once [`tile_count`] is known
+/// to be small.
+pub fn tile_urls(server: &TileServer, bounds: GeoBounds, max_zoom: u32) -> Vec<String> {
+    let template = server.url.replace("{s}", SUBDOMAIN);
+    let mut urls = Vec::new();
+    for zoom in offline_zooms(server, max_zoom) {
+        let (columns, rows) = tile_range(bounds, zoom);
+        for x in columns {
+            for y in rows.clone() {
+                urls.push(
+                    template
+                        .replace("{z}", &zoom.to_string())
+                        .replace("{x}", &(x % (1 << zoom)).to_string())
+                        .replace("{y}", &y.to_string()),
+                );
+            }
+        }
+    }
+    urls
+}
+
+/// Puts the tiles into the cache the service worker serves them from.
+///
+/// Tiles already cached are skipped. `progress` is called after every tile with the
+/// number done so far, the number of tiles that failed is returned.
+pub async fn download(urls: &[String], progress: impl Fn(usize)) -> Result<usize, JsValue> {
+    let caches = window().ok_or(JsValue::from_str("no window"))?.caches()?;
+    let cache: Cache = JsFuture::from(caches.open(TILE_CACHE)).await?.unchecked_into();
+    let mut failed = 0;
+    for (done, url) in urls.iter().enumerate() {
+        let cached = JsFuture::from(cache.match_with_str(url)).await?;
+        if cached.is_undefined() && JsFuture::from(cache.add_with_str(url)).await.is_err() {
+            failed += 1;
+        }
+        progress(done + 1);
+    }
+    Ok(failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> TileServer {
+        TileSource::Custom.server("https://tiles.example/{z}/{x}/{y}.png")
+    }
+
+    fn bounds(south: f64, west: f64, north: f64, east: f64) -> GeoBounds {
+        GeoBounds {
+            south,
+            west,
+            north,
+            east,
+        }
+    }
+
+    #[test]
+    fn tiles_of_the_whole_world() {
+        let world = bounds(-90.0, -180.0, 90.0, 180.0);
+        assert_eq!(tile_count(&server(), world, MIN_OFFLINE_ZOOM), 1 << (2 * MIN_OFFLINE_ZOOM));
+        // counting stays cheap and exact at the highest zoom
+        let total: u64 = (MIN_OFFLINE_ZOOM..=MAX_NATIVE_ZOOM).map(|zoom| 1 << (2 * zoom)).sum();
+        assert_eq!(tile_count(&server(), world, MAX_NATIVE_ZOOM), total);
+        // a view panned round the world several times still counts each tile once
+        assert_eq!(tile_count(&server(), bounds(-90.0, -540.0, 90.0, 540.0), 12), 1 << 24);
+    }
+
+    #[test]
+    fn tiles_at_the_poles() {
+        assert_eq!(tile_y(90.0, 12), 0);
+        assert_eq!(tile_y(85.0511, 12), 0);
+        assert_eq!(tile_y(-90.0, 12), 4095);
+        assert_eq!(tile_y(0.0, 12), 2048);
+        // beyond the edge of the web mercator map there is one row of tiles left
+        assert_eq!(tile_count(&server(), bounds(86.0, 0.0, 90.0, 0.05), 12), 1);
+        assert_eq!(tile_count(&server(), bounds(-90.0, 0.0, -86.0, 0.05), 12), 1);
+    }
+
+    #[test]
+    fn tiles_across_the_antimeridian() {
+        // 4094 and 4095 west of the antimeridian, 0 and 1 east of it
+        let columns = |bounds: GeoBounds| -> Vec<String> {
+            tile_urls(&server(), bounds, 12)
+                .iter()
+                .map(|url| url.split('/').nth(4).unwrap().to_string())
+                .collect()
+        };
+        let expected = vec!["4094", "4095", "0", "1"];
+        // as Leaflet reports it after panning east across the antimeridian
+        assert_eq!(columns(bounds(0.001, 179.9, 0.01, 180.1)), expected);
+        // normalised bounds, whose east edge lies west of the west edge
+        assert_eq!(columns(bounds(0.001, 179.9, 0.01, -179.9)), expected);
+        // the same view one world further west
+        assert_eq!(columns(bounds(0.001, -180.1, 0.01, -179.9)), expected);
+        assert_eq!(tile_count(&server(), bounds(0.001, 179.9, 0.01, -179.9), 12), 4);
+    }
+
+    #[test]
+    fn offline_zoom_levels() {
+        let area = bounds(52.0, 9.0, 52.001, 9.001);
+        assert_eq!(tile_count(&server(), area, MIN_OFFLINE_ZOOM - 1), 0);
+        assert_eq!(tile_count(&server(), area, MIN_OFFLINE_ZOOM), 1);
+        // zoom levels beyond what the server renders are not downloaded
+        let topplus = TileSource::TopPlusOpen.server("");
+        assert_eq!(tile_count(&topplus, area, 19), tile_count(&topplus, area, 18));
+        let urls = tile_urls(&server(), area, 13);
+        assert_eq!(urls.len() as u64, tile_count(&server(), area, 13));
+        assert!(urls[0].starts_with("https://tiles.example/12/"));
+    }
+}
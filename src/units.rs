@@ -0,0 +1,240 @@
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use sycamore::prelude::*;
+
+use crate::components::SelectOption;
+
+/// Metres in a US survey foot, still used for state plane coordinates.
+const US_SURVEY_FOOT: f64 = 1200.0 / 3937.0;
+const FOOT: f64 = 0.3048;
+const MILE: f64 = 1609.344;
+const ACRE_IN_SQUARE_FEET: f64 = 43560.0;
+
+/// Unit a value is entered or shown in, settings are always stored in the SI base unit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Unit {
+    /// no unit, like a zoom level
+    #[default]
+    Plain,
+    Metre,
+    Centimetre,
+    Foot,
+    Inch,
+    UsSurveyFoot,
+    Second,
+    MetrePerSecond,
+    KilometrePerHour,
+    MilePerHour,
+    MetrePerSecondSquared,
+    FootPerSecondSquared,
+    SquareMetre,
+    Hectare,
+    /// also used with US survey feet, the 6 ppm difference is far below what coverage resolves
+    SquareFoot,
+    Acre,
+    UsSurveyAcre,
+}
+
+const LENGTHS: [Unit; 5] = [Unit::Metre, Unit::Centimetre, Unit::Foot, Unit::Inch, Unit::UsSurveyFoot];
+const SPEEDS: [Unit; 3] = [Unit::MetrePerSecond, Unit::KilometrePerHour, Unit::MilePerHour];
+
+impl Unit {
+    /// Base units in one of this unit.
+    pub fn factor(&self) -> f64 {
+        match self {
+            Unit::Centimetre => 0.01,
+            Unit::Foot => FOOT,
+            Unit::Inch => FOOT / 12.0,
+            Unit::UsSurveyFoot => US_SURVEY_FOOT,
+            Unit::KilometrePerHour => 1.0 / 3.6,
+            Unit::MilePerHour => MILE / 3600.0,
+            Unit::FootPerSecondSquared => FOOT,
+            Unit::Hectare => 10_000.0,
+            Unit::SquareFoot => FOOT * FOOT,
+            Unit::Acre => ACRE_IN_SQUARE_FEET * FOOT * FOOT,
+            Unit::UsSurveyAcre => ACRE_IN_SQUARE_FEET * US_SURVEY_FOOT * US_SURVEY_FOOT,
+            _ => 1.0,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Unit::Plain => "",
+            Unit::Metre => "m",
+            Unit::Centimetre => "cm",
+            Unit::Foot => "ft",
+            Unit::Inch => "in",
+            Unit::UsSurveyFoot => "ft (US)",
+            Unit::Second => "s",
+            Unit::MetrePerSecond => "m/s",
+            Unit::KilometrePerHour => "km/h",
+            Unit::MilePerHour => "mph",
+            Unit::MetrePerSecondSquared => "m/s²",
+            Unit::FootPerSecondSquared => "ft/s²",
+            Unit::SquareMetre => "m²",
+            Unit::Hectare => "ha",
+            Unit::SquareFoot => "ft²",
+            Unit::Acre => "ac",
+            Unit::UsSurveyAcre => "ac (US)",
+        }
+    }
+
+    /// Units of the same quantity the value can be converted to, empty if there are none.
+    pub fn alternatives(&self) -> &'static [Unit] {
+        if LENGTHS.contains(self) {
+            &LENGTHS
+        } else if SPEEDS.contains(self) {
+            &SPEEDS
+        } else {
+            &[]
+        }
+    }
+
+    /// `value` in this unit in the base unit.
+    pub fn to_base(self, value: f64) -> f64 {
+        value * self.factor()
+    }
+
+    /// `value` in the base unit in this unit.
+    pub fn convert(self, value: f64) -> f64 {
+        value / self.factor()
+    }
+
+    /// The unit `system` uses where the metric system uses this one.
+    pub fn in_system(self, system: UnitSystem) -> Unit {
+        let survey = system == UnitSystem::UsSurvey;
+        match (system, self) {
+            (UnitSystem::Metric, unit) => unit,
+            (_, Unit::Metre) if survey => Unit::UsSurveyFoot,
+            (_, Unit::Metre) => Unit::Foot,
+            (_, Unit::Centimetre) => Unit::Inch,
+            (_, Unit::MetrePerSecond | Unit::KilometrePerHour) => Unit::MilePerHour,
+            (_, Unit::MetrePerSecondSquared) => Unit::FootPerSecondSquared,
+            (_, Unit::SquareMetre) => Unit::SquareFoot,
+            (_, Unit::Hectare) if survey => Unit::UsSurveyAcre,
+            (_, Unit::Hectare) => Unit::Acre,
+            (_, unit) => unit,
+        }
+    }
+}
+
+/// Units the values are shown in, everything is stored metric.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnitSystem {
+    #[default]
+    Metric,
+    Imperial,
+    /// imperial, but lengths in US survey feet as used by state plane coordinates
+    UsSurvey,
+}
+
+impl SelectOption for UnitSystem {
+    fn all() -> Vec<Self> {
+        vec![UnitSystem::Metric, UnitSystem::Imperial, UnitSystem::UsSurvey]
+    }
+
+    fn lable(&self) -> String {
+        match self {
+            UnitSystem::Metric => t!("units_metric"),
+            UnitSystem::Imperial => t!("units_imperial"),
+            UnitSystem::UsSurvey => t!("units_us_survey"),
+        }
+    }
+}
+
+impl UnitSystem {
+    /// Area unit fitting the size of `square_metres`, hectares or acres for whole fields.
+    pub fn area_unit(&self, square_metres: f64) -> Unit {
+        let large = Unit::Hectare.in_system(*self);
+        if square_metres < large.factor() {
+            Unit::SquareMetre.in_system(*self)
+        } else {
+            large
+        }
+    }
+
+    /// `square_metres` as number in a fitting unit, whole square metres or feet and
+    /// hundredths of larger units.
+    pub fn area(&self, square_metres: f64) -> (String, Unit) {
+        let unit = self.area_unit(square_metres);
+        let decimals = if unit == Unit::SquareMetre.in_system(*self) { 0 } else { 2 };
+        (format!("{:.*}", decimals, unit.convert(square_metres)), unit)
+    }
+
+    /// `square_metres` with a fitting unit.
+    pub fn format_area(&self, square_metres: f64) -> String {
+        let (number, unit) = self.area(square_metres);
+        format!("{} {}", number, unit.symbol())
+    }
+}
+
+/// The unit system picked in the settings, provided by the app.
+pub fn use_unit_system() -> ReadSignal<UnitSystem> {
+    use_context::<ReadSignal<UnitSystem>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::assert_close;
+
+    #[test]
+    fn lengths() {
+        assert_close(Unit::Foot.to_base(1.0), 0.3048, 1e-9);
+        assert_close(Unit::Inch.to_base(12.0), 0.3048, 1e-9);
+        assert_close(Unit::Centimetre.convert(0.5), 50.0, 1e-9);
+        assert_close(Unit::Metre.to_base(2.5), 2.5, 1e-9);
+        assert_close(Unit::Inch.convert(Unit::Inch.to_base(7.3)), 7.3, 1e-9);
+    }
+
+    #[test]
+    fn alternatives() {
+        assert!(Unit::Foot.alternatives().contains(&Unit::Centimetre));
+        assert!(Unit::Metre.alternatives().contains(&Unit::Inch));
+        assert!(Unit::Second.alternatives().is_empty());
+        assert!(Unit::Plain.alternatives().is_empty());
+    }
+
+    #[test]
+    fn imperial_conversions() {
+        assert_close(Unit::UsSurveyFoot.convert(1200.0), 3937.0, 1e-9);
+        assert_close(Unit::MilePerHour.to_base(1.0), 0.44704, 1e-9);
+        assert_close(Unit::KilometrePerHour.to_base(3.6), 1.0, 1e-9);
+        assert_close(Unit::FootPerSecondSquared.convert(0.3048), 1.0, 1e-9);
+        assert_close(Unit::Acre.to_base(1.0), 4_046.856_422_4, 1e-9);
+        assert!((Unit::UsSurveyAcre.to_base(1.0) - 4_046.872_6).abs() < 1e-4);
+    }
+
+    #[test]
+    fn units_in_system() {
+        assert_eq!(Unit::Metre.in_system(UnitSystem::Metric), Unit::Metre);
+        assert_eq!(Unit::Metre.in_system(UnitSystem::Imperial), Unit::Foot);
+        assert_eq!(
+            Unit::Metre.in_system(UnitSystem::UsSurvey),
+            Unit::UsSurveyFoot
+        );
+        assert_eq!(Unit::Centimetre.in_system(UnitSystem::UsSurvey), Unit::Inch);
+        assert_eq!(
+            Unit::KilometrePerHour.in_system(UnitSystem::Imperial),
+            Unit::MilePerHour
+        );
+        assert_eq!(
+            Unit::Hectare.in_system(UnitSystem::UsSurvey),
+            Unit::UsSurveyAcre
+        );
+        assert_eq!(Unit::Second.in_system(UnitSystem::Imperial), Unit::Second);
+    }
+
+    #[test]
+    fn areas() {
+        assert_eq!(UnitSystem::Metric.format_area(500.4), "500 m²");
+        assert_eq!(UnitSystem::Metric.format_area(12_345.0), "1.23 ha");
+        assert_eq!(UnitSystem::Imperial.format_area(1000.0), "10764 ft²");
+        assert_eq!(UnitSystem::Imperial.format_area(4100.0), "1.01 ac");
+        assert_eq!(UnitSystem::UsSurvey.format_area(10_000.0), "2.47 ac (US)");
+        assert_eq!(
+            UnitSystem::Imperial.area(4000.0),
+            (String::from("43056"), Unit::SquareFoot)
+        );
+    }
+}
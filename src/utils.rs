@@ -1,6 +1,8 @@
 use gloo_net::http::Request;
-use sycamore::prelude::*;
-use web_sys::{window, console};
+use gloo_timers::callback::Timeout;
+use js_sys::Array;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{window, console, Blob, BlobPropertyBag, HtmlAnchorElement, Url};
 use serde::{ Deserialize, Serialize};
 
 
@@ -72,6 +74,15 @@ pub fn set_stored_text(key: &str, value: String) {
     }
 }
 
+#[allow(dead_code)]
+pub fn remove_stored_item(key: &str) {
+    if let Some(win) = window() {
+        if let Ok(Some(stor)) = win.local_storage() {
+            let _ = stor.remove_item(key);
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub fn get_stored_item<T>(key: &str, default_value: T) -> T
 where
@@ -99,16 +110,45 @@ where
 }
 
 #[allow(dead_code)]
-pub fn create_stored_signal<T>(key: String, default_value: T) -> Signal<T>
-where
-    T: for<'a> Deserialize<'a> + Serialize + Clone,
-{
-    let result = create_signal(get_stored_item(key.as_str(), default_value));
-    create_effect(move || set_stored_item(key.as_str(), result.with(|c| (*c).clone())));
-    result
+pub fn log_to_browser(log_msg: String) {
+    console::log_1(&log_msg.into());
 }
 
+/// Time in milliseconds the browser gets to read a download before its URL is revoked.
+const REVOKE_DELAY: u32 = 60_000;
+
+fn object_url(mime_type: &str, content: &str) -> Result<String, JsValue> {
+    let mut options = BlobPropertyBag::new();
+    options.type_(mime_type);
+    let blob = Blob::new_with_str_sequence_and_options(&Array::of1(&JsValue::from_str(content)), &options)?;
+    Url::create_object_url_with_blob(&blob)
+}
+
+/// Lets the browser save `content` as a file named `file_name`.
 #[allow(dead_code)]
-pub fn log_to_browser(log_msg: String) {
-    console::log_1(&log_msg.into());
-}
\ No newline at end of file
+pub fn download_file(file_name: &str, mime_type: &str, content: &str) -> Result<(), JsValue> {
+    let document = window()
+        .and_then(|win| win.document())
+        .ok_or(JsValue::from_str("no document"))?;
+    let url = object_url(mime_type, content)?;
+    let link: HtmlAnchorElement = document.create_element("a")?.unchecked_into();
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+    // some browsers only start reading the blob after click() returned
+    Timeout::new(REVOKE_DELAY, move || {
+        let _ = Url::revoke_object_url(&url);
+    })
+    .forget();
+    Ok(())
+}
+
+/// Shows the HTML page `html` in a new tab, e.g. to print it.
+#[allow(dead_code)]
+pub fn open_html(html: &str) -> Result<(), JsValue> {
+    let win = window().ok_or(JsValue::from_str("no window"))?;
+    // not revoked, the new tab loads it after this returns
+    let url = object_url("text/html", html)?;
+    win.open_with_url_and_target(&url, "_blank")?;
+    Ok(())
+}
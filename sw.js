@@ -1,6 +1,34 @@
-const cacheName = "site-cache";
+const cacheName = "site-cache-v3";
+// must match TILE_CACHE in src/tiles.rs
+const tileCacheName = "tile-cache";
 
-const contentToCache = [];
+const contentToCache = [
+  "./",
+  "./index.html",
+  "./manifest.json",
+  "./logo.svg",
+  // loaded by leaflet.css, not listed in index.html
+  "./assets/leaflet/images/layers.png",
+  "./assets/leaflet/images/layers-2x.png",
+  "./assets/leaflet/images/marker-icon.png",
+  "./assets/leaflet/images/marker-icon-2x.png",
+  "./assets/leaflet/images/marker-shadow.png",
+];
+
+// Trunk puts a hash into the names of the wasm bundle and its loader, so they are
+// taken from the built index.html instead of being listed here.
+async function bundleUrls() {
+  const response = await fetch("./index.html", { cache: "no-store" });
+  const html = await response.text();
+  const urls = new Set();
+  for (const match of html.matchAll(/(?:href|src)=["']([^"']+)["']|from\s+["']([^"']+)["']|init\(["']([^"']+)["']/g)) {
+    const url = new URL(match[1] || match[2] || match[3], self.location.href);
+    if (url.origin === self.location.origin) {
+      urls.add(url.href);
+    }
+  }
+  return [...urls];
+}
 
 self.addEventListener("install", (e) => {
   console.log("[Service Worker] Install");
@@ -8,15 +36,74 @@ self.addEventListener("install", (e) => {
     (async () => {
       const cache = await caches.open(cacheName);
       console.log("[Service Worker] Caching all: app shell and content");
-      await cache.addAll(contentToCache);
+      // addAll rejects everything if one file is missing, so a broken asset would
+      // leave the app without any offline copy
+      const urls = [...contentToCache, ...(await bundleUrls())];
+      const results = await Promise.allSettled(urls.map((url) => cache.add(url)));
+      results.forEach((result, i) => {
+        if (result.status === "rejected") {
+          console.warn("[Service Worker] Not cached:", urls[i], result.reason);
+        }
+      });
+      await self.skipWaiting();
     })()
   );
 });
 
-self.addEventListener("fetch", (e) => {
-  e.respondWith(
+self.addEventListener("activate", (e) => {
+  e.waitUntil(
     (async () => {
-      return await fetch(e.request);
+      for (const key of await caches.keys()) {
+        if (key !== cacheName && key !== tileCacheName) {
+          await caches.delete(key);
+        }
+      }
+      await self.clients.claim();
     })()
   );
-});
\ No newline at end of file
+});
+
+// Tiles rarely change, so the cached ones are used without asking the network.
+async function tile(request) {
+  const cache = await caches.open(tileCacheName);
+  const cached = await cache.match(request);
+  if (cached) {
+    return cached;
+  }
+  const response = await fetch(request);
+  if (response.ok) {
+    await cache.put(request, response.clone());
+  }
+  return response;
+}
+
+// Everything else comes from the network while there is one, so updates arrive.
+async function networkFirst(request) {
+  const cache = await caches.open(cacheName);
+  try {
+    const response = await fetch(request);
+    if (response.ok || response.type === "opaque") {
+      await cache.put(request, response.clone());
+    }
+    return response;
+  } catch (err) {
+    const cached = await cache.match(request, { ignoreSearch: true });
+    if (cached) {
+      return cached;
+    }
+    if (request.mode === "navigate") {
+      return await cache.match("./index.html");
+    }
+    throw err;
+  }
+}
+
+self.addEventListener("fetch", (e) => {
+  if (e.request.method !== "GET") {
+    return;
+  }
+  // the tile source is a setting, so map tiles are told apart by being images from elsewhere
+  const url = new URL(e.request.url);
+  const isTile = url.origin !== self.location.origin && e.request.destination === "image";
+  e.respondWith(isTile ? tile(e.request) : networkFirst(e.request));
+});